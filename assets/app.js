@@ -1,3 +1,15 @@
+// colors defined as CSS variables in app.css, so charts follow custom theme
+const cssVar = (name, fallback) =>
+  getComputedStyle(document.documentElement).getPropertyValue(name).trim() || fallback;
+
+const chartColors = () => ({
+  primary: cssVar('--ghs-chart-primary', 'rgb(54, 162, 235)'),
+  primaryFill: cssVar('--ghs-chart-primary-fill', 'rgba(54, 162, 235, 0.5)'),
+  secondary: cssVar('--ghs-chart-secondary', 'rgb(255, 99, 132)'),
+  secondaryFill: cssVar('--ghs-chart-secondary-fill', 'rgba(255, 99, 132, 0.5)'),
+  cursor: cssVar('--ghs-chart-cursor', 'rgba(0, 0, 255, 0.4)'),
+});
+
 // https://stackoverflow.com/a/68140000/3664464
 const mouseLinePlugin = {
   afterDraw: chart => {
@@ -10,7 +22,7 @@ const mouseLinePlugin = {
     ctx.moveTo(x, yAxis.top);
     ctx.lineTo(x, yAxis.bottom);
     ctx.lineWidth = 1;
-    ctx.strokeStyle = 'rgba(0, 0, 255, 0.4)';
+    ctx.strokeStyle = chartColors().cursor;
     ctx.stroke();
     ctx.restore();
   },
@@ -18,13 +30,25 @@ const mouseLinePlugin = {
 
 const renderMetrics = (canvasId, metrics, uniqueCol, countCol) => {
   const ctx = document.getElementById(canvasId);
+  const colors = chartColors();
+  const bar = { borderWidth: 0, borderRadius: 4 };
   new Chart(ctx, {
     type: 'bar',
     data: {
       labels: metrics.map(x => x.date.split('T')[0]),
       datasets: [
-        { label: 'Unique', data: metrics.map(x => x[uniqueCol]), borderWidth: 0, borderRadius: 4 },
-        { label: 'Count', data: metrics.map(x => x[countCol]), borderWidth: 0, borderRadius: 4 },
+        {
+          ...bar,
+          label: 'Unique',
+          data: metrics.map(x => x[uniqueCol]),
+          backgroundColor: colors.primaryFill,
+        },
+        {
+          ...bar,
+          label: 'Count',
+          data: metrics.map(x => x[countCol]),
+          backgroundColor: colors.secondaryFill,
+        },
       ],
     },
     options: {
@@ -44,13 +68,23 @@ const renderMetrics = (canvasId, metrics, uniqueCol, countCol) => {
   });
 };
 
-const renderStars = (canvasId, stars) => {
+const renderStars = (canvasId, stars, col = 'stars') => {
   const ctx = document.getElementById(canvasId);
+  const colors = chartColors();
   new Chart(ctx, {
     type: 'line',
     data: {
       labels: stars.map(x => x.date.split('T')[0]),
-      datasets: [{ label: '', data: stars.map(x => x.stars), pointStyle: false, tension: 0.0 }],
+      datasets: [
+        {
+          label: '',
+          data: stars.map(x => x[col]),
+          pointStyle: false,
+          tension: 0.0,
+          borderColor: colors.primary,
+          backgroundColor: colors.primaryFill,
+        },
+      ],
     },
     options: {
       responsive: true,
@@ -69,3 +103,72 @@ const renderStars = (canvasId, stars) => {
     plugins: [mouseLinePlugin],
   });
 };
+
+const renderCompare = (canvasId, items, src, col) => {
+  const ctx = document.getElementById(canvasId);
+  new Chart(ctx, {
+    type: 'line',
+    data: {
+      datasets: items.map(x => ({
+        label: x.name,
+        data: x[src].map(p => ({ x: p.date.split('T')[0], y: p[col] })),
+        pointStyle: false,
+        tension: 0.0,
+      })),
+    },
+    options: {
+      responsive: true,
+      interaction: { mode: 'nearest', axis: 'x', intersect: false },
+      scales: {
+        x: { type: 'time', time: { tooltipFormat: 'yyyy-MM-dd' } },
+        y: { beginAtZero: true },
+      },
+      plugins: {
+        legend: { display: true, position: 'bottom' },
+        tooltip: { intersect: false },
+      },
+    },
+    plugins: [mouseLinePlugin],
+  });
+};
+
+// keyboard navigation of repos table: j / k – next / prev row, Enter – open repo, / – search
+const isTyping = el =>
+  el.isContentEditable || ['INPUT', 'TEXTAREA', 'SELECT'].includes(el.tagName);
+
+document.addEventListener('keydown', e => {
+  if (e.ctrlKey || e.metaKey || e.altKey || isTyping(e.target)) return;
+
+  if (e.key === '/') {
+    const search = document.querySelector('input[type="search"]');
+    if (!search) return;
+    e.preventDefault();
+    search.focus();
+    return;
+  }
+
+  // table replaced by htmx on sort / page change, so rows looked up on each key
+  const rows = [...document.querySelectorAll('#repos_table tbody tr')];
+  if (!rows.length) return;
+  const idx = rows.findIndex(x => x.classList.contains('row-focused'));
+
+  if (e.key === 'j' || e.key === 'k') {
+    e.preventDefault();
+    const next = e.key === 'j' ? Math.min(idx + 1, rows.length - 1) : Math.max(idx - 1, 0);
+    rows[idx]?.classList.remove('row-focused');
+    rows[next].classList.add('row-focused');
+    rows[next].scrollIntoView({ block: 'nearest' });
+  }
+
+  if (e.key === 'Escape' && idx >= 0) {
+    rows[idx].classList.remove('row-focused');
+  }
+
+  // focused link / button handles Enter itself
+  if (e.key === 'Enter' && idx >= 0 && e.target === document.body) {
+    const link = rows[idx].querySelector('a[href]');
+    if (!link) return;
+    e.preventDefault();
+    link.click();
+  }
+});
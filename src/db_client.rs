@@ -1,12 +1,17 @@
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::pin::Pin;
 
 use anyhow::Ok;
 use serde::{Deserialize, Serialize};
 use serde_variant::to_variant_name;
-use sqlx::{sqlite::SqliteConnectOptions, FromRow, SqlitePool};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::{FromRow, SqlitePool};
 
-use crate::gh_client::{PullRequest, Repo, RepoClones, RepoPopularPath, RepoReferrer, RepoViews};
+use crate::gh_client::{
+  IssueCounts, PullRequest, Repo, RepoClones, RepoPopularPath, RepoReferrer, RepoViews,
+  TrafficDaily,
+};
 use crate::types::Res;
 
 // MARK: Migrations
@@ -97,12 +102,176 @@ async fn migrate_v3(db: &SqlitePool) -> Res {
   Ok(())
 }
 
+async fn migrate_v4(db: &SqlitePool) -> Res {
+  let queries = vec![
+    "ALTER TABLE repo_stats ADD COLUMN issues_open INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE repo_stats ADD COLUMN issues_closed INTEGER NOT NULL DEFAULT 0;",
+  ];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v5(db: &SqlitePool) -> Res {
+  let queries = vec![
+    "ALTER TABLE repo_stats ADD COLUMN size INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE repos ADD COLUMN default_branch TEXT DEFAULT NULL;",
+  ];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v6(db: &SqlitePool) -> Res {
+  // previous names of renamed repos, to redirect from old urls
+  let qs = "CREATE TABLE IF NOT EXISTS repo_renames (
+    name TEXT NOT NULL PRIMARY KEY,
+    repo_id INTEGER NOT NULL
+  );";
+
+  let _ = sqlx::query(qs).execute(db).await?;
+  Ok(())
+}
+
+async fn migrate_v7(db: &SqlitePool) -> Res {
+  let queries = vec!["ALTER TABLE repos ADD COLUMN private BOOLEAN DEFAULT FALSE;"];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v8(db: &SqlitePool) -> Res {
+  let queries = vec![
+    "ALTER TABLE repos ADD COLUMN created_at TEXT DEFAULT NULL;",
+    "ALTER TABLE repos ADD COLUMN pushed_at TEXT DEFAULT NULL;",
+  ];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v9(db: &SqlitePool) -> Res {
+  let queries = vec!["ALTER TABLE repo_stats ADD COLUMN downloads INTEGER NOT NULL DEFAULT 0;"];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v10(db: &SqlitePool) -> Res {
+  let queries = vec![
+    "CREATE TABLE IF NOT EXISTS repo_topics (
+      repo_id INTEGER NOT NULL,
+      topic TEXT NOT NULL,
+      PRIMARY KEY (repo_id, topic),
+      FOREIGN KEY (repo_id) REFERENCES repos(id)
+    );",
+    "CREATE INDEX IF NOT EXISTS idx_repo_topics_topic ON repo_topics(topic);",
+  ];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v11(db: &SqlitePool) -> Res {
+  // lookup of previous date of same referrer / path for incremental deltas
+  let queries = vec![
+    "CREATE INDEX IF NOT EXISTS idx_repo_referrers_key ON repo_referrers(repo_id, referrer, date);",
+    "CREATE INDEX IF NOT EXISTS idx_repo_popular_paths_key ON repo_popular_paths(repo_id, path, date);",
+  ];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v12(db: &SqlitePool) -> Res {
+  let queries = vec!["ALTER TABLE repos ADD COLUMN watched BOOLEAN DEFAULT FALSE;"];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v13(db: &SqlitePool) -> Res {
+  // for already tracked repos estimated by first collected traffic day (stars history goes back
+  // before tracking start, so used only if no traffic at all)
+  let queries = vec![
+    "ALTER TABLE repos ADD COLUMN first_seen TEXT DEFAULT NULL;",
+    "UPDATE repos SET first_seen = COALESCE(
+      (SELECT MIN(date) FROM repo_stats WHERE repo_id = repos.id AND (views_count > 0 OR clones_count > 0)),
+      (SELECT MIN(date) FROM repo_stats WHERE repo_id = repos.id)
+    );",
+  ];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  Ok(())
+}
+
+async fn migrate_v14(db: &SqlitePool) -> Res {
+  // merge referrers reported in different case or with trailing slash (same as `insert_referrers`)
+  let queries = vec![
+    "CREATE TABLE repo_referrers_merged AS
+      SELECT repo_id, date, LOWER(RTRIM(TRIM(referrer), '/')) AS referrer,
+        SUM(count) AS count, MAX(uniques) AS uniques
+      FROM repo_referrers GROUP BY 1, 2, 3;",
+    "DELETE FROM repo_referrers;",
+    "INSERT INTO repo_referrers (repo_id, date, referrer, count, uniques)
+      SELECT repo_id, date, referrer, count, uniques FROM repo_referrers_merged;",
+    "DROP TABLE repo_referrers_merged;",
+  ];
+
+  for qs in queries {
+    let _ = sqlx::query(qs).execute(db).await?;
+  }
+
+  let qs = deltas_query("repo_referrers", "referrer", 0);
+  let _ = sqlx::query(&qs).execute(db).await?;
+
+  Ok(())
+}
+
 async fn migrate<'a>(db: &'a SqlitePool) -> Res {
   type BoxFn = Box<dyn for<'a> Fn(&'a SqlitePool) -> Pin<Box<dyn Future<Output = Res> + 'a>>>;
   let migrations: Vec<BoxFn> = vec![
     Box::new(|db| Box::pin(migrate_v1(db))),
     Box::new(|db| Box::pin(migrate_v2(db))),
     Box::new(|db| Box::pin(migrate_v3(db))),
+    Box::new(|db| Box::pin(migrate_v4(db))),
+    Box::new(|db| Box::pin(migrate_v5(db))),
+    Box::new(|db| Box::pin(migrate_v6(db))),
+    Box::new(|db| Box::pin(migrate_v7(db))),
+    Box::new(|db| Box::pin(migrate_v8(db))),
+    Box::new(|db| Box::pin(migrate_v9(db))),
+    Box::new(|db| Box::pin(migrate_v10(db))),
+    Box::new(|db| Box::pin(migrate_v11(db))),
+    Box::new(|db| Box::pin(migrate_v12(db))),
+    Box::new(|db| Box::pin(migrate_v13(db))),
+    Box::new(|db| Box::pin(migrate_v14(db))),
   ];
 
   let version: (i32,) = sqlx::query_as("PRAGMA user_version").fetch_one(db).await?;
@@ -122,9 +291,33 @@ async fn migrate<'a>(db: &'a SqlitePool) -> Res {
 }
 
 pub async fn get_db(db_path: &str) -> Res<SqlitePool> {
-  let opts = SqliteConnectOptions::new().filename(db_path).create_if_missing(true);
-  let pool = SqlitePool::connect_with(opts).await?;
-  migrate(&pool).await?;
+  if let Some(parent) = std::path::Path::new(db_path).parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+
+  // WAL lets UI read while cron writes, busy timeout makes concurrent writers wait for lock
+  let busy_timeout = std::env::var("GHS_DB_BUSY_TIMEOUT").unwrap_or_default();
+  let busy_timeout = busy_timeout.parse::<u64>().ok().filter(|x| *x > 0).unwrap_or(30);
+
+  let opts = SqliteConnectOptions::new()
+    .filename(db_path)
+    .create_if_missing(true)
+    .journal_mode(SqliteJournalMode::Wal)
+    .synchronous(SqliteSynchronous::Normal)
+    .busy_timeout(std::time::Duration::from_secs(busy_timeout));
+
+  // sqlite allows single writer anyway, so few connections enough for UI reads + cron writes
+  let max_conn = std::env::var("GHS_DB_MAX_CONN").unwrap_or_default();
+  let max_conn = max_conn.parse::<u32>().ok().filter(|x| *x > 0).unwrap_or(5);
+  tracing::info!("db pool size: {}", max_conn);
+
+  // migrations run on own single connection: pooled connection opened in the middle of migrations
+  // keeps stale schema and caches wrong columns for `SELECT *` (sqlx panics on such rows)
+  let mig = SqlitePoolOptions::new().max_connections(1).connect_with(opts.clone()).await?;
+  migrate(&mig).await?;
+  mig.close().await;
+
+  let pool = SqlitePoolOptions::new().max_connections(max_conn).connect_with(opts).await?;
   Ok(pool)
 }
 
@@ -137,16 +330,29 @@ pub struct RepoTotals {
   pub description: Option<String>,
   pub fork: bool,
   pub archived: bool,
+  pub private: bool,
+  pub hidden: bool,
+  pub watched: bool,
+  pub default_branch: Option<String>,
+  pub created_at: Option<String>,
+  pub pushed_at: Option<String>,
+  pub first_seen: Option<String>, // when ghstats started tracking repo
   pub date: String,
   pub stars: i32,
   pub forks: i32,
   pub watchers: i32,
   pub issues: i32,
+  pub issues_open: i32,
+  pub issues_closed: i32,
   pub prs: i32,
+  pub size: i32,
+  pub downloads: i32,
   pub clones_count: i32,
   pub clones_uniques: i32,
   pub views_count: i32,
   pub views_uniques: i32,
+  #[sqlx(default)]
+  pub stars_new: i32, // only in repos list, for `stars_days` window
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
@@ -164,6 +370,62 @@ pub struct RepoStars {
   pub stars: i32,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct RepoForks {
+  pub date: String,
+  pub forks: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct RepoIssues {
+  pub date: String,
+  pub issues: i32, // open issues (without PRs)
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, FromRow)]
+pub struct RepoDelta {
+  pub repo_id: i64,
+  pub date: String,
+  pub stars: i32,
+  pub clones_count: i32,
+  pub clones_uniques: i32,
+  pub views_count: i32,
+  pub views_uniques: i32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, FromRow)]
+pub struct RepoTrackingSummary {
+  pub since: String,
+  pub days: i64,
+  pub stars: i32, // gained since first day
+  pub clones_count: i32,
+  pub views_count: i32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, FromRow)]
+pub struct RepoWeekday {
+  pub weekday: i32, // 0 = Sunday, as SQLite `%w`
+  pub days: i32,    // number of days with data for this weekday
+  pub clones_count: i32,
+  pub views_count: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct TrafficSpike {
+  pub name: String,
+  pub date: String,
+  pub views_count: i32,
+  pub avg_views: f64, // over previous days
+  #[serde(skip)]
+  pub fork: bool,
+  #[serde(skip)]
+  pub archived: bool,
+  #[serde(skip)]
+  pub private: bool,
+  #[serde(skip)]
+  pub stars: i32,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
 pub struct RepoPopularItem {
   pub name: String,
@@ -171,6 +433,14 @@ pub struct RepoPopularItem {
   pub uniques: i64,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct RepoGap {
+  pub name: String,
+  pub gap_start: String,
+  pub gap_end: String,
+  pub days: i64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
 pub struct RepoItem {
   pub id: i64,
@@ -179,6 +449,120 @@ pub struct RepoItem {
   pub stars_synced: bool,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct ExportRepo {
+  pub id: i64,
+  pub name: String,
+  pub description: Option<String>,
+  pub archived: bool,
+  pub fork: bool,
+  pub private: bool,
+  pub hidden: bool,
+  pub stars_synced: bool,
+  pub default_branch: Option<String>,
+  pub created_at: Option<String>,
+  pub pushed_at: Option<String>,
+  #[serde(default)]
+  pub first_seen: Option<String>,
+  #[serde(default)]
+  pub watched: bool,
+  #[sqlx(skip)]
+  #[serde(default)]
+  pub topics: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct ExportStats {
+  pub repo_id: i64,
+  pub date: String,
+  pub stars: i32,
+  pub forks: i32,
+  pub watchers: i32,
+  pub issues: i32,
+  pub issues_open: i32,
+  pub issues_closed: i32,
+  pub prs: i32,
+  pub size: i32,
+  #[serde(default)]
+  pub downloads: i32,
+  pub clones_count: i32,
+  pub clones_uniques: i32,
+  pub views_count: i32,
+  pub views_uniques: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct ExportStatsLine {
+  pub repo: String,
+  #[sqlx(flatten)]
+  #[serde(flatten)]
+  pub stats: ExportStats,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct ExportPopular {
+  pub repo_id: i64,
+  pub date: String,
+  pub name: String,
+  pub title: Option<String>, // only for paths
+  pub count: i32,
+  pub uniques: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct ExportRename {
+  pub name: String, // previous name
+  pub repo_id: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportData {
+  pub version: i32,
+  pub repos: Vec<ExportRepo>,
+  pub stats: Vec<ExportStats>,
+  pub referrers: Vec<ExportPopular>,
+  pub paths: Vec<ExportPopular>,
+  #[serde(default)]
+  pub renames: Vec<ExportRename>,
+}
+
+/// First line of JSONL export
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportHeader {
+  pub version: i32,
+}
+
+impl ExportHeader {
+  /// Check snapshot made with same schema version as database
+  pub fn validate(&self, version: i32) -> Res {
+    if self.version != version {
+      anyhow::bail!("schema version mismatch: snapshot v{}, database v{}", self.version, version);
+    }
+
+    Ok(())
+  }
+}
+
+impl ExportData {
+  /// Check snapshot can be imported as whole: same schema version & no rows of unknown repos
+  pub fn validate(&self, version: i32) -> Res {
+    ExportHeader { version: self.version }.validate(version)?;
+
+    let ids = self.repos.iter().map(|x| x.id).collect::<HashSet<_>>();
+    let refs = self.stats.iter().map(|x| x.repo_id);
+    let refs = refs.chain(self.referrers.iter().map(|x| x.repo_id));
+    let refs = refs.chain(self.paths.iter().map(|x| x.repo_id));
+    let refs = refs.chain(self.renames.iter().map(|x| x.repo_id));
+    for repo_id in refs {
+      if !ids.contains(&repo_id) {
+        anyhow::bail!("unknown repo_id {} (not in repos list)", repo_id);
+      }
+    }
+
+    Ok(())
+  }
+}
+
 // MARK: Filters
 
 pub enum PopularKind {
@@ -186,7 +570,7 @@ pub enum PopularKind {
   Path,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum Direction {
   Asc,
@@ -205,19 +589,37 @@ impl std::fmt::Display for Direction {
   }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum RepoSort {
   Name,
   Stars,
   Forks,
   Watchers,
+  #[serde(rename = "issues_open", alias = "issues")]
   Issues,
   Prs,
-  #[serde(rename = "clones_count")]
+  #[serde(rename = "clones_count", alias = "clones")]
   Clones,
-  #[serde(rename = "views_count")]
+  #[serde(rename = "views_count", alias = "views")]
   Views,
+  #[serde(rename = "pushed_at", alias = "pushed")]
+  Pushed,
+  #[serde(rename = "stars_new")]
+  StarsNew,
+  ClonesUniques,
+  ViewsUniques,
+}
+
+impl RepoSort {
+  /// Sort by uniques of same metric (shown in same column as count)
+  pub fn uniques(&self) -> Option<RepoSort> {
+    match self {
+      RepoSort::Clones => Some(RepoSort::ClonesUniques),
+      RepoSort::Views => Some(RepoSort::ViewsUniques),
+      _ => None,
+    }
+  }
 }
 
 impl Default for RepoSort {
@@ -257,6 +659,49 @@ impl std::fmt::Display for PopularSort {
 pub struct RepoFilter {
   pub sort: RepoSort,
   pub direction: Direction,
+  pub page: Option<u32>,
+  pub per_page: Option<u32>,
+  pub include_private: Option<bool>,
+  pub active: Option<bool>,
+  #[serde(deserialize_with = "de_flag")]
+  pub show_hidden: bool, // also list repos no longer accessible with token
+  pub stars_days: Option<u32>,
+  pub topic: Option<String>,
+  pub owner: Option<String>,
+  #[serde(deserialize_with = "de_flag")]
+  pub anonymize: bool, // UI only, private repo names replaced with aliases
+}
+
+impl RepoFilter {
+  /// Window for new stars column, 30 days by default
+  pub fn stars_days(&self) -> u32 {
+    self.stars_days.filter(|x| *x > 0).unwrap_or(30)
+  }
+
+  /// Number of pages for given items count (always 1 if pagination not used)
+  pub fn pages_count(&self, total: usize) -> usize {
+    match self.per_page {
+      Some(per_page) if per_page > 0 => total.div_ceil(per_page as usize).max(1),
+      _ => 1,
+    }
+  }
+
+  /// Slice items for current page, all items returned if `per_page` not set
+  pub fn paginate<T>(&self, items: Vec<T>) -> Vec<T> {
+    let per_page = match self.per_page {
+      Some(per_page) if per_page > 0 => per_page as usize,
+      _ => return items,
+    };
+
+    let page = self.page.unwrap_or(1).max(1) as usize;
+    items.into_iter().skip((page - 1) * per_page).take(per_page).collect()
+  }
+}
+
+/// Query flag from link (`1` / `true`) or checkbox (`on`)
+pub(crate) fn de_flag<'de, D: serde::Deserializer<'de>>(de: D) -> Result<bool, D::Error> {
+  let val = String::deserialize(de)?.to_lowercase();
+  Result::Ok(matches!(val.as_str(), "1" | "true" | "on"))
 }
 
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -265,39 +710,162 @@ pub struct PopularFilter {
   pub sort: PopularSort,
   pub direction: Direction,
   pub period: i32,
+  #[serde(deserialize_with = "de_flag")]
+  pub exclude_root: bool, // only for paths, hide repo homepage `/owner/repo`
+  #[serde(deserialize_with = "de_flag")]
+  pub anonymize: bool, // hide private repo name on repo page (redirects to alias url)
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct MetricsFilter {
+  pub range: i32,
 }
 
 // MARK: DbClient
 
-const TOTAL_QUERY: &'static str = "
-SELECT * FROM repos r
-INNER JOIN (
-	SELECT
-		rs.repo_id,
-		SUM(clones_count) AS clones_count, SUM(clones_uniques) AS clones_uniques,
-    SUM(views_count) AS views_count, SUM(views_uniques) AS views_uniques,
-    latest.*
-	FROM repo_stats rs
-	INNER JOIN (
-		SELECT repo_id, MAX(date) AS date, stars, forks, watchers, issues, prs
-		FROM repo_stats GROUP BY repo_id
-	) latest ON latest.repo_id = rs.repo_id
-	GROUP BY rs.repo_id
-) rs ON rs.repo_id = r.id
+/// Repos with latest stats and traffic totals, summed for last `window` days (all time if <= 0)
+fn total_query(window: i32) -> String {
+  let sum = |col: &str| match window {
+    x if x > 0 => {
+      format!("SUM(CASE WHEN rs.date >= date('now', '-{x} day') THEN {col} ELSE 0 END)")
+    }
+    _ => format!("SUM({col})"),
+  };
+
+  let (clones_count, clones_uniques) = (sum("clones_count"), sum("clones_uniques"));
+  let (views_count, views_uniques) = (sum("views_count"), sum("views_uniques"));
+
+  #[rustfmt::skip]
+  let qs = format!("
+  SELECT * FROM repos r
+  INNER JOIN (
+    SELECT
+      rs.repo_id,
+      {clones_count} AS clones_count, {clones_uniques} AS clones_uniques,
+      {views_count} AS views_count, {views_uniques} AS views_uniques,
+      latest.*
+    FROM repo_stats rs
+    INNER JOIN (
+      SELECT repo_id, MAX(date) AS date, stars, forks, watchers, issues, issues_open, issues_closed, prs, size, downloads
+      FROM repo_stats GROUP BY repo_id
+    ) latest ON latest.repo_id = rs.repo_id
+    GROUP BY rs.repo_id
+  ) rs ON rs.repo_id = r.id
+  ");
+  qs
+}
+
+// traffic stored per day, so delta is just latest row; stars stored as totals, so diff with
+// previous row (skipped when previous row has no stars, e.g. first day or traffic-only row)
+const DELTA_QUERY: &str = "
+WITH cte AS (
+  SELECT
+    rs.repo_id, rs.date, rs.stars, rs.clones_count, rs.clones_uniques, rs.views_count, rs.views_uniques,
+    LAG(rs.stars) OVER (PARTITION BY rs.repo_id ORDER BY rs.date) AS prev_stars,
+    ROW_NUMBER() OVER (PARTITION BY rs.repo_id ORDER BY rs.date DESC) AS rn
+  FROM repo_stats rs
+)
+SELECT
+  cte.repo_id, cte.date,
+  CASE WHEN COALESCE(cte.prev_stars, 0) > 0 THEN cte.stars - cte.prev_stars ELSE 0 END AS stars,
+  cte.clones_count, cte.clones_uniques, cte.views_count, cte.views_uniques
+FROM cte
+INNER JOIN repos r ON r.id = cte.repo_id
+WHERE r.hidden = FALSE AND cte.rn = 1
 ";
 
+/// Subquery for last known stars of repo before last `days` days (new stars = current - this)
+fn new_stars_query(repo_id: &str, days: u32) -> String {
+  #[rustfmt::skip]
+  let qs = format!("
+  SELECT stars FROM repo_stats
+  WHERE repo_id = {repo_id} AND stars > 0 AND date < date('now', '-{days} day')
+  ORDER BY date DESC LIMIT 1
+  ");
+  qs
+}
+
+/// Query to update referrers / paths deltas for last `dates` dates (all if 0)
+#[rustfmt::skip]
+fn deltas_query(table: &str, col: &str, dates: i32) -> String {
+  match dates {
+    x if x > 0 => format!("
+    WITH cte AS (
+    SELECT
+      rr.repo_id, rr.date, rr.{col},
+      MAX(0, rr.uniques - COALESCE(pr.uniques, 0)) AS uniques_delta,
+      MAX(0, rr.count - COALESCE(pr.count, 0)) AS count_delta
+    FROM {table} rr
+    LEFT JOIN {table} pr ON pr.repo_id = rr.repo_id AND pr.{col} = rr.{col} AND pr.date = (
+      SELECT MAX(date) FROM {table}
+      WHERE repo_id = rr.repo_id AND {col} = rr.{col} AND date < rr.date
+    )
+    WHERE rr.date >= COALESCE((
+      SELECT date FROM (SELECT DISTINCT date FROM {table} ORDER BY date DESC LIMIT 1 OFFSET {offset})
+    ), '')
+    )
+    UPDATE {table} AS rr SET
+      uniques_delta = cte.uniques_delta,
+      count_delta = cte.count_delta
+    FROM cte
+    WHERE rr.repo_id = cte.repo_id AND rr.date = cte.date AND rr.{col} = cte.{col};
+    ", offset = x - 1),
+    _ => format!("
+    WITH cte AS (
+    SELECT
+      rr.repo_id, rr.date, rr.{col}, rr.uniques, rr.count,
+      LAG(rr.uniques) OVER (PARTITION BY rr.repo_id, rr.{col} ORDER BY rr.date) AS prev_uniques,
+      LAG(rr.count) OVER (PARTITION BY rr.repo_id, rr.{col} ORDER BY rr.date) AS prev_count
+    FROM {table} rr
+    )
+    UPDATE {table} AS rr	SET
+      uniques_delta = MAX(0, cte.uniques - COALESCE(cte.prev_uniques, 0)),
+      count_delta = MAX(0, cte.count - COALESCE(cte.prev_count, 0))
+    FROM cte
+    WHERE rr.repo_id = cte.repo_id AND rr.date = cte.date AND rr.{col} = cte.{col};
+    "),
+  }
+}
+
 pub struct DbClient {
   db: SqlitePool,
+  totals_window: i32,
+  deltas_dates: i32,
 }
 
 impl DbClient {
   pub async fn new(db_path: &str) -> Res<Self> {
     let db = get_db(db_path).await?;
-    Ok(Self { db })
+
+    // clones / views totals for last N days (like GitHub Insights) instead of all time
+    let totals_window = std::env::var("GHS_TOTALS_WINDOW").unwrap_or_default();
+    let totals_window = totals_window.parse::<i32>().ok().filter(|x| *x > 0).unwrap_or(0);
+
+    // recompute referrers / paths deltas only for last N dates on each sync (0 for all)
+    let deltas_dates = std::env::var("GHS_DELTAS_DATES").unwrap_or_default();
+    let deltas_dates = deltas_dates.parse::<i32>().ok().filter(|x| *x > 0).unwrap_or(0);
+
+    Ok(Self { db, totals_window, deltas_dates })
+  }
+
+  /// Days clones / views totals summed for (0 for all time)
+  pub fn totals_window(&self) -> i32 {
+    self.totals_window
+  }
+
+  fn total_query(&self) -> String {
+    total_query(self.totals_window)
   }
 
   // MARK: Getters
 
+  /// Check database is reachable
+  pub async fn ping(&self) -> Res {
+    sqlx::query("SELECT 1;").execute(&self.db).await?;
+    Ok(())
+  }
+
   pub async fn get_repos_ids(&self) -> Res<Vec<i64>> {
     let qs = "SELECT id FROM repos WHERE hidden = FALSE;";
     let items: Vec<(i64,)> = sqlx::query_as(qs).fetch_all(&self.db).await?;
@@ -305,62 +873,436 @@ impl DbClient {
   }
 
   pub async fn get_repo_totals(&self, repo: &str) -> Res<Option<RepoTotals>> {
-    let qs = format!("{} WHERE r.hidden = FALSE AND r.name = $1;", TOTAL_QUERY);
+    let qs = format!("{} WHERE r.hidden = FALSE AND r.name = $1;", self.total_query());
     let item = sqlx::query_as(qs.as_str()).bind(repo).fetch_optional(&self.db).await?;
     Ok(item)
   }
 
-  pub async fn get_metrics(&self, repo: &str) -> Res<Vec<RepoMetrics>> {
+  /// Get current name of repo, if given name is one of its previous names
+  pub async fn get_renamed_repo(&self, old_name: &str) -> Res<Option<String>> {
     let qs = "
-    SELECT * FROM repo_stats rs
-    INNER JOIN repos r ON r.id = rs.repo_id
-    WHERE r.hidden = FALSE AND r.name = $1 AND (rs.clones_count > 0 OR rs.views_count > 0)
-    ORDER BY rs.date ASC;
+    SELECT r.name FROM repo_renames rn
+    INNER JOIN repos r ON r.id = rn.repo_id
+    WHERE r.hidden = FALSE AND rn.name = $1 AND r.name != $1;
     ";
 
-    let items = sqlx::query_as(qs).bind(repo).fetch_all(&self.db).await?;
-    Ok(items)
+    let item: Option<(String,)> =
+      sqlx::query_as(qs).bind(old_name).fetch_optional(&self.db).await?;
+    Ok(item.map(|x| x.0))
   }
 
-  pub async fn get_repos(&self, filter: &RepoFilter) -> Res<Vec<RepoTotals>> {
-    let qs = format!(
-      "{} WHERE r.hidden = FALSE ORDER BY {} {}",
-      TOTAL_QUERY, filter.sort, filter.direction
-    );
-    let items = sqlx::query_as(qs.as_str()).fetch_all(&self.db).await?;
-    Ok(items)
+  /// Get change of repo stats since previous day (zeros if repo has only one day of data)
+  pub async fn get_daily_delta(&self, repo: &str) -> Res<Option<RepoDelta>> {
+    let qs = format!("{} AND r.name = $1;", DELTA_QUERY);
+    let item = sqlx::query_as(qs.as_str()).bind(repo).fetch_optional(&self.db).await?;
+    Ok(item)
+  }
+
+  /// Get change of stats since previous day for all repos
+  pub async fn get_daily_deltas(&self) -> Res<HashMap<i64, RepoDelta>> {
+    let items: Vec<RepoDelta> = sqlx::query_as(DELTA_QUERY).fetch_all(&self.db).await?;
+    Ok(items.into_iter().map(|x| (x.repo_id, x)).collect())
   }
 
-  pub async fn get_stars(&self, repo: &str) -> Res<Vec<RepoStars>> {
+  /// Get totals collected by ghstats since first stored day of repo
+  pub async fn get_tracking_summary(&self, repo: &str) -> Res<Option<RepoTrackingSummary>> {
+    // stars history is loaded from repo creation, so start is first day with traffic
+    // traffic-only rows have no stars, so stars taken from last stored value at each date
     let qs = "
-    SELECT date, stars FROM repo_stats rs
-    INNER JOIN repos r ON r.id = rs.repo_id
-    WHERE r.hidden = FALSE AND r.name = $1
-    ORDER BY rs.date ASC;
+    WITH cte AS (
+      SELECT rs.* FROM repo_stats rs
+      INNER JOIN repos r ON r.id = rs.repo_id
+      WHERE r.hidden = FALSE AND r.name = $1 AND rs.date >= (
+        SELECT MIN(date) FROM repo_stats
+        WHERE repo_id = rs.repo_id AND (clones_count > 0 OR views_count > 0)
+      )
+    ), agg AS (
+      SELECT
+        repo_id,
+        MIN(date) AS since,
+        CAST(julianday(MAX(date)) - julianday(MIN(date)) + 1 AS INTEGER) AS days,
+        SUM(clones_count) AS clones_count,
+        SUM(views_count) AS views_count
+      FROM cte
+      GROUP BY repo_id
+    )
+    SELECT
+      since,
+      days,
+      COALESCE((
+        SELECT stars FROM repo_stats WHERE repo_id = agg.repo_id AND stars > 0
+        ORDER BY date DESC LIMIT 1
+      ), 0) - COALESCE((
+        SELECT stars FROM repo_stats WHERE repo_id = agg.repo_id AND stars > 0 AND date <= since
+        ORDER BY date DESC LIMIT 1
+      ), 0) AS stars,
+      clones_count,
+      views_count
+    FROM agg;
     ";
 
-    let mut items: Vec<RepoStars> = sqlx::query_as(qs).bind(repo).fetch_all(&self.db).await?;
+    let item = sqlx::query_as(qs).bind(repo).fetch_optional(&self.db).await?;
+    Ok(item)
+  }
 
-    // restore gaps in data
-    let mut prev_stars = 0;
-    for (idx, item) in items.iter_mut().enumerate() {
-      if idx == 0 {
-        continue;
-      }
+  /// Get daily traffic of repo for last `range` days (all time if `range` <= 0)
+  pub async fn get_metrics(&self, repo: &str, range: i32) -> Res<Vec<RepoMetrics>> {
+    let time_where = match range {
+      x if x > 0 => format!("rs.date >= date('now', '-{} day')", x),
+      _ => "1=1".to_string(),
+    };
 
-      if item.stars == 0 {
-        item.stars = prev_stars;
+    #[rustfmt::skip]
+    let qs = format!("
+    SELECT * FROM repo_stats rs
+    INNER JOIN repos r ON r.id = rs.repo_id
+    WHERE r.hidden = FALSE AND r.name = $1 AND (rs.clones_count > 0 OR rs.views_count > 0)
+      AND {time_where}
+    ORDER BY rs.date ASC;
+    ");
+
+    let items = sqlx::query_as(&qs).bind(repo).fetch_all(&self.db).await?;
+    Ok(items)
+  }
+
+  pub async fn get_aggregate_metrics(&self, repos_ids: &[i64]) -> Res<Vec<RepoMetrics>> {
+    let ids = repos_ids.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",");
+
+    #[rustfmt::skip]
+    let qs = format!("
+    SELECT
+      date,
+      SUM(clones_count) AS clones_count, SUM(clones_uniques) AS clones_uniques,
+      SUM(views_count) AS views_count, SUM(views_uniques) AS views_uniques
+    FROM repo_stats
+    WHERE repo_id IN ({ids})
+    GROUP BY date
+    HAVING SUM(clones_count) > 0 OR SUM(views_count) > 0
+    ORDER BY date ASC;
+    ");
+
+    let items = sqlx::query_as(&qs).fetch_all(&self.db).await?;
+    Ok(items)
+  }
+
+  /// Get total stars of given repos by day
+  ///
+  /// Repos have different date ranges & gaps, so each repo contributes its last known value
+  /// (carried forward until next row) and 0 before its first row. Rows with 0 stars are gaps
+  /// (traffic collected without stars), so they are skipped as in `get_stars`.
+  pub async fn get_aggregate_stars(&self, repos_ids: &[i64]) -> Res<Vec<RepoStars>> {
+    let ids = repos_ids.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",");
+
+    #[rustfmt::skip]
+    let qs = format!("
+    SELECT repo_id, date, stars FROM repo_stats
+    WHERE repo_id IN ({ids}) AND stars > 0
+    ORDER BY date ASC;
+    ");
+
+    let rows: Vec<(i64, String, i32)> = sqlx::query_as(&qs).fetch_all(&self.db).await?;
+
+    let mut last: HashMap<i64, i32> = HashMap::new();
+    let mut total = 0;
+    let mut items: Vec<RepoStars> = vec![];
+    for (repo_id, date, stars) in rows {
+      total += stars - last.insert(repo_id, stars).unwrap_or(0);
+      match items.last_mut() {
+        Some(x) if x.date == date => x.stars = total,
+        _ => items.push(RepoStars { date, stars: total }),
+      }
+    }
+
+    Ok(items)
+  }
+
+  /// Daily stars of last `days` days for all repos with synced stars history (by `repo_id`)
+  pub async fn get_recent_stars_all(&self, days: u32) -> Res<HashMap<i64, Vec<i32>>> {
+    let since = (chrono::Utc::now() - chrono::Duration::days(days as i64)).format("%Y-%m-%d");
+
+    let qs = "
+    SELECT rs.repo_id, rs.stars FROM repo_stats rs
+    INNER JOIN repos r ON r.id = rs.repo_id
+    WHERE r.stars_synced = TRUE AND rs.date >= $1
+    ORDER BY rs.repo_id ASC, rs.date ASC;
+    ";
+
+    let rows: Vec<(i64, i32)> =
+      sqlx::query_as(qs).bind(since.to_string()).fetch_all(&self.db).await?;
+
+    let mut items: HashMap<i64, Vec<i32>> = HashMap::new();
+    for (repo_id, stars) in rows {
+      let values = items.entry(repo_id).or_default();
+      // restore gaps in data (days with traffic only), leading ones are skipped
+      match (stars, values.last()) {
+        (0, Some(&prev)) => values.push(prev),
+        (0, None) => {}
+        (x, _) => values.push(x),
+      }
+    }
+
+    Ok(items)
+  }
+
+  pub async fn get_repos(&self, filter: &RepoFilter) -> Res<Vec<RepoTotals>> {
+    let private_where = match filter.include_private {
+      Some(false) => "AND r.private = FALSE",
+      _ => "",
+    };
+
+    // totals of traffic over all stored days
+    let active_where = match filter.active {
+      Some(true) => "AND (rs.views_count > 0 OR rs.clones_count > 0)",
+      _ => "",
+    };
+
+    let collate = match filter.sort {
+      RepoSort::Name => "COLLATE NOCASE",
+      _ => "",
+    };
+
+    // repos no longer accessible with token are hidden, but can be listed on demand
+    let hidden_where = match filter.show_hidden {
+      true => "1=1",
+      false => "r.hidden = FALSE",
+    };
+
+    // user input passed as query params, numbered in order of appearance
+    let mut binds: Vec<String> = vec![];
+
+    let topic = filter.topic.as_deref().map(|x| x.trim().to_lowercase()).filter(|x| !x.is_empty());
+    let topic_where = match topic {
+      Some(topic) => {
+        binds.push(topic);
+        format!("AND r.id IN (SELECT repo_id FROM repo_topics WHERE topic = ${})", binds.len())
+      }
+      None => "".to_string(),
+    };
+
+    let owner = filter.owner.as_deref().map(|x| x.trim()).filter(|x| !x.is_empty());
+    let owner_where = match owner {
+      Some(owner) => {
+        let owner = owner.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        binds.push(format!("{}/%", owner));
+        format!("AND r.name LIKE ${} ESCAPE '\\'", binds.len())
+      }
+      None => "".to_string(),
+    };
+
+    let total_query = self.total_query();
+    let stars_before = new_stars_query("t.id", filter.stars_days());
+    let (sort, direction) = (&filter.sort, &filter.direction);
+
+    #[rustfmt::skip]
+    let qs = format!("
+    SELECT t.*, t.stars - COALESCE(({stars_before}), 0) AS stars_new
+    FROM ({total_query} WHERE {hidden_where} {private_where} {active_where} {topic_where} {owner_where}) t
+    ORDER BY {sort} {collate} {direction}
+    ");
+
+    let mut query = sqlx::query_as(qs.as_str());
+    for bind in &binds {
+      query = query.bind(bind);
+    }
+
+    let items = query.fetch_all(&self.db).await?;
+    Ok(items)
+  }
+
+  /// Get repos which latest day views exceed average of previous `days` days by `factor` times
+  pub async fn get_traffic_spikes(
+    &self,
+    factor: f64,
+    days: i32,
+    min_views: i32,
+  ) -> Res<Vec<TrafficSpike>> {
+    #[rustfmt::skip]
+    let qs = format!("
+    WITH cte AS (
+      SELECT
+        rs.repo_id, r.name, r.fork, r.archived, r.private, rs.stars, rs.date, rs.views_count,
+        ROW_NUMBER() OVER (PARTITION BY rs.repo_id ORDER BY rs.date DESC) AS rn
+      FROM repo_stats rs
+      INNER JOIN repos r ON r.id = rs.repo_id
+      WHERE r.hidden = FALSE
+    ),
+    latest AS (
+      SELECT cte.*, (
+        SELECT AVG(pr.views_count) FROM repo_stats pr
+        WHERE pr.repo_id = cte.repo_id AND pr.date < cte.date
+          AND julianday(pr.date) >= julianday(cte.date) - {days}
+      ) AS avg_views
+      FROM cte WHERE rn = 1
+    )
+    SELECT name, fork, archived, private, stars, date, views_count, avg_views FROM latest
+    WHERE avg_views IS NOT NULL AND views_count >= $2 AND views_count > avg_views * $1
+    ORDER BY views_count DESC;
+    ");
+
+    let items = sqlx::query_as(&qs).bind(factor).bind(min_views).fetch_all(&self.db).await?;
+    Ok(items)
+  }
+
+  /// Aliases `private-repo-N` for private repos (numbered by id, so stable between views)
+  pub async fn get_private_aliases(&self) -> Res<HashMap<i64, String>> {
+    let qs = "SELECT id FROM repos WHERE private = TRUE ORDER BY id;";
+    let items: Vec<(i64,)> = sqlx::query_as(qs).fetch_all(&self.db).await?;
+    let items = items.into_iter().enumerate();
+    Ok(items.map(|(idx, x)| (x.0, format!("private-repo-{}", idx + 1))).collect())
+  }
+
+  /// Name of private repo by its `private-repo-N` alias
+  pub async fn get_repo_by_alias(&self, alias: &str) -> Res<Option<String>> {
+    let idx = alias.strip_prefix("private-repo-").and_then(|x| x.parse::<i64>().ok());
+    let idx = match idx {
+      Some(x) if x > 0 => x,
+      _ => return Ok(None),
+    };
+
+    let qs = "SELECT name FROM repos WHERE private = TRUE ORDER BY id LIMIT 1 OFFSET $1;";
+    let item: Option<(String,)> = sqlx::query_as(qs).bind(idx - 1).fetch_optional(&self.db).await?;
+    Ok(item.map(|x| x.0))
+  }
+
+  /// Get GitHub topics of repo, sorted by name
+  pub async fn get_repo_topics(&self, repo: &str) -> Res<Vec<String>> {
+    let qs = "
+    SELECT rt.topic FROM repo_topics rt
+    INNER JOIN repos r ON r.id = rt.repo_id
+    WHERE r.hidden = FALSE AND r.name = $1
+    ORDER BY rt.topic;
+    ";
+
+    let items: Vec<(String,)> = sqlx::query_as(qs).bind(repo).fetch_all(&self.db).await?;
+    Ok(items.into_iter().map(|x| x.0).collect())
+  }
+
+  /// Get stars gained by repo over last `days` days
+  pub async fn get_new_stars(&self, repo: &str, days: u32) -> Res<i32> {
+    let stars_before = new_stars_query("r.id", days);
+
+    #[rustfmt::skip]
+    let qs = format!("
+    SELECT rs.stars - COALESCE(({stars_before}), 0) FROM repos r
+    INNER JOIN repo_stats rs ON rs.repo_id = r.id
+    WHERE r.hidden = FALSE AND r.name = $1 AND rs.stars > 0
+    ORDER BY rs.date DESC LIMIT 1;
+    ");
+
+    let item: Option<(i32,)> = sqlx::query_as(&qs).bind(repo).fetch_optional(&self.db).await?;
+    Ok(item.map(|x| x.0).unwrap_or(0))
+  }
+
+  /// Sum of clones & views over last `days` days (`date` is latest day in window)
+  pub async fn get_window_metrics(&self, repo: &str, days: u32) -> Res<RepoMetrics> {
+    #[rustfmt::skip]
+    let qs = format!("
+    SELECT
+      COALESCE(MAX(rs.date), '') AS date,
+      COALESCE(SUM(rs.clones_count), 0) AS clones_count,
+      COALESCE(SUM(rs.clones_uniques), 0) AS clones_uniques,
+      COALESCE(SUM(rs.views_count), 0) AS views_count,
+      COALESCE(SUM(rs.views_uniques), 0) AS views_uniques
+    FROM repo_stats rs
+    INNER JOIN repos r ON r.id = rs.repo_id
+    WHERE r.hidden = FALSE AND r.name = $1 AND rs.date >= date('now', '-{offset} day');
+    ", offset = days.saturating_sub(1)); // today included, so window starts days - 1 ago
+
+    let item = sqlx::query_as(&qs).bind(repo).fetch_one(&self.db).await?;
+    Ok(item)
+  }
+
+  /// Clones & views summed by day of week since traffic collection started (always 7 items)
+  pub async fn get_weekday_distribution(&self, repo: &str) -> Res<Vec<RepoWeekday>> {
+    #[rustfmt::skip]
+    let qs = "
+    SELECT
+      CAST(strftime('%w', rs.date) AS INTEGER) AS weekday,
+      COUNT(*) AS days,
+      SUM(rs.clones_count) AS clones_count,
+      SUM(rs.views_count) AS views_count
+    FROM repo_stats rs
+    INNER JOIN repos r ON r.id = rs.repo_id
+    WHERE r.hidden = FALSE AND r.name = $1 AND rs.date >= (
+      SELECT MIN(x.date) FROM repo_stats x
+      WHERE x.repo_id = r.id AND (x.views_count > 0 OR x.clones_count > 0)
+    )
+    GROUP BY weekday;
+    ";
+
+    let rows: Vec<RepoWeekday> = sqlx::query_as(qs).bind(repo).fetch_all(&self.db).await?;
+
+    // fill weekdays without data, so result is always Sun..Sat
+    let items = (0..7)
+      .map(|x| match rows.iter().find(|r| r.weekday == x) {
+        Some(r) => r.clone(),
+        None => RepoWeekday { weekday: x, ..Default::default() },
+      })
+      .collect();
+
+    Ok(items)
+  }
+
+  /// Get daily history of `col` for last `range` days (all time if `range` <= 0)
+  async fn get_history(&self, repo: &str, col: &str, range: i32) -> Res<Vec<(String, i32)>> {
+    #[rustfmt::skip]
+    let qs = format!("
+    SELECT date, {col} FROM repo_stats rs
+    INNER JOIN repos r ON r.id = rs.repo_id
+    WHERE r.hidden = FALSE AND r.name = $1
+    ORDER BY rs.date ASC;
+    ");
+
+    let rows: Vec<(String, i32)> = sqlx::query_as(&qs).bind(repo).fetch_all(&self.db).await?;
+
+    // restore days without rows with last known value (zero in existing row is real value)
+    let day = |x: &str| chrono::NaiveDate::parse_from_str(x.get(..10)?, "%Y-%m-%d").ok();
+    let mut items: Vec<(String, i32)> = Vec::with_capacity(rows.len());
+    for (date, value) in rows {
+      if let (Some((prev_date, prev_value)), Some(till)) = (items.last().cloned(), day(&date)) {
+        let mut next = day(&prev_date).and_then(|x| x.succ_opt());
+        while let Some(x) = next.filter(|x| *x < till) {
+          items.push((format!("{}T00:00:00Z", x.format("%Y-%m-%d")), prev_value));
+          next = x.succ_opt();
+        }
       }
 
-      prev_stars = item.stars;
+      items.push((date, value));
     }
 
     // in case when data start to be collected for exist repo with some stats
-    // view and clone stats can be collected without stars, so remove them
-    let items = items.into_iter().filter(|x| x.stars > 0).collect();
+    // view and clone stats can be collected without stars / forks, so remove them
+    let items = items.into_iter().skip_while(|x| x.1 == 0);
+
+    // filtered after gaps restored to not lose count before range start
+    let since = match range {
+      x if x > 0 => (chrono::Utc::now() - chrono::Duration::days(x as i64)).format("%Y-%m-%d"),
+      _ => return Ok(items.collect()),
+    };
+
+    let since = since.to_string();
+    let items = items.filter(|x| x.0 >= since).collect();
     Ok(items)
   }
 
+  /// Get stars history of repo for last `range` days (all time if `range` <= 0)
+  pub async fn get_stars(&self, repo: &str, range: i32) -> Res<Vec<RepoStars>> {
+    let items = self.get_history(repo, "stars", range).await?;
+    Ok(items.into_iter().map(|(date, stars)| RepoStars { date, stars }).collect())
+  }
+
+  /// Get forks history of repo for last `range` days (all time if `range` <= 0)
+  pub async fn get_forks(&self, repo: &str, range: i32) -> Res<Vec<RepoForks>> {
+    let items = self.get_history(repo, "forks", range).await?;
+    Ok(items.into_iter().map(|(date, forks)| RepoForks { date, forks }).collect())
+  }
+
+  /// Get open issues history of repo for last `range` days (all time if `range` <= 0)
+  pub async fn get_issues_history(&self, repo: &str, range: i32) -> Res<Vec<RepoIssues>> {
+    let items = self.get_history(repo, "issues", range).await?;
+    Ok(items.into_iter().map(|(date, issues)| RepoIssues { date, issues }).collect())
+  }
+
   pub async fn get_popular_items(
     &self,
     repo: &str,
@@ -377,6 +1319,11 @@ impl DbClient {
       _ => "1=1".to_string(),
     };
 
+    let root_where = match (kind, filter.exclude_root) {
+      (PopularKind::Path, true) => format!("rr.{col} != $2"),
+      _ => "1=1".to_string(),
+    };
+
     let order_by = format!("{} {}", filter.sort, filter.direction);
 
     #[rustfmt::skip]
@@ -384,12 +1331,42 @@ impl DbClient {
     SELECT {col} as name, SUM(count_delta) AS count, SUM(uniques_delta) AS uniques
     FROM {table} rr
     INNER JOIN repos r ON r.id = rr.repo_id
-    WHERE r.hidden = FALSE AND r.name = $1 AND {time_where}
+    WHERE r.hidden = FALSE AND r.name = $1 AND {time_where} AND {root_where}
     GROUP BY rr.{col}
     ORDER BY {order_by};
     ");
 
-    let items = sqlx::query_as(&qs).bind(repo).fetch_all(&self.db).await?;
+    let root = format!("/{}", repo);
+    let items = sqlx::query_as(&qs).bind(repo).bind(root).fetch_all(&self.db).await?;
+    Ok(items)
+  }
+
+  /// Find missing days in collected stats after first day with traffic (all repos if `None`)
+  pub async fn detect_gaps(&self, repo: Option<&str>) -> Res<Vec<RepoGap>> {
+    let qs = "
+    WITH first AS (
+      SELECT repo_id, MIN(date) AS first_date FROM repo_stats
+      WHERE clones_count > 0 OR views_count > 0
+      GROUP BY repo_id
+    ),
+    cte AS (
+      SELECT
+        r.name, rs.date,
+        LAG(rs.date) OVER (PARTITION BY rs.repo_id ORDER BY rs.date) AS prev_date
+      FROM repo_stats rs
+      INNER JOIN repos r ON r.id = rs.repo_id
+      INNER JOIN first f ON f.repo_id = rs.repo_id
+      WHERE r.hidden = FALSE AND rs.date >= f.first_date AND ($1 IS NULL OR r.name = $1)
+    )
+    SELECT
+      name, prev_date AS gap_start, date AS gap_end,
+      CAST(julianday(date) - julianday(prev_date) - 1 AS INTEGER) AS days
+    FROM cte
+    WHERE julianday(date) - julianday(prev_date) > 1
+    ORDER BY name ASC, date ASC;
+    ";
+
+    let items = sqlx::query_as(qs).bind(repo).fetch_all(&self.db).await?;
     Ok(items)
   }
 
@@ -399,17 +1376,254 @@ impl DbClient {
     Ok(items)
   }
 
+  // MARK: Export
+
+  pub async fn get_version(&self) -> Res<i32> {
+    let version: (i32,) = sqlx::query_as("PRAGMA user_version").fetch_one(&self.db).await?;
+    Ok(version.0)
+  }
+
+  pub async fn export_data(&self) -> Res<ExportData> {
+    let qs = "SELECT * FROM repos ORDER BY id;";
+    let repos = sqlx::query_as(qs).fetch_all(&self.db).await?;
+
+    let qs = "SELECT * FROM repo_stats ORDER BY repo_id, date;";
+    let stats = sqlx::query_as(qs).fetch_all(&self.db).await?;
+
+    let qs = "
+    SELECT repo_id, date, referrer AS name, NULL AS title, count, uniques
+    FROM repo_referrers ORDER BY repo_id, date;
+    ";
+    let referrers = sqlx::query_as(qs).fetch_all(&self.db).await?;
+
+    let qs = "
+    SELECT repo_id, date, path AS name, title, count, uniques
+    FROM repo_popular_paths ORDER BY repo_id, date;
+    ";
+    let paths = sqlx::query_as(qs).fetch_all(&self.db).await?;
+
+    let qs = "SELECT name, repo_id FROM repo_renames ORDER BY name;";
+    let renames = sqlx::query_as(qs).fetch_all(&self.db).await?;
+
+    let mut repos: Vec<ExportRepo> = repos;
+    let qs = "SELECT repo_id, topic FROM repo_topics ORDER BY repo_id, topic;";
+    let topics: Vec<(i64, String)> = sqlx::query_as(qs).fetch_all(&self.db).await?;
+    for (repo_id, topic) in topics {
+      if let Some(x) = repos.iter_mut().find(|x| x.id == repo_id) {
+        x.topics.push(topic);
+      }
+    }
+
+    let version = self.get_version().await?;
+    Ok(ExportData { version, repos, stats, referrers, paths, renames })
+  }
+
+  /// Page of daily stats with repo name, keyset paginated by (repo_id, date)
+  pub async fn export_stats_page(
+    &self,
+    after: Option<&(i64, String)>,
+    limit: i32,
+  ) -> Res<Vec<ExportStatsLine>> {
+    let (repo_id, date) = match after {
+      Some((repo_id, date)) => (*repo_id, date.as_str()),
+      None => (i64::MIN, ""),
+    };
+
+    let qs = "
+    SELECT r.name AS repo, rs.* FROM repo_stats rs
+    INNER JOIN repos r ON r.id = rs.repo_id
+    WHERE (rs.repo_id, rs.date) > ($1, $2)
+    ORDER BY rs.repo_id, rs.date
+    LIMIT $3;
+    ";
+
+    let items = sqlx::query_as(qs).bind(repo_id).bind(date).bind(limit).fetch_all(&self.db).await?;
+    Ok(items)
+  }
+
+  /// Upsert exported snapshot in single transaction (nothing saved if any row fails)
+  pub async fn import_data(&self, data: &ExportData) -> Res {
+    let tx = self.begin().await?;
+    let res = tx.import_data_rows(data).await;
+    tx.finish(res).await?;
+    self.update_deltas_full().await
+  }
+
+  async fn import_data_rows(&self, data: &ExportData) -> Res {
+    // fields not set by sync kept as in snapshot, earliest first_seen wins
+    let qs = "
+    UPDATE repos SET
+      hidden = $2,
+      stars_synced = stars_synced OR $3,
+      first_seen = COALESCE(MIN(first_seen, $4), $4, first_seen)
+    WHERE id = $1;
+    ";
+
+    for x in &data.repos {
+      let repo = Repo {
+        id: x.id as u64,
+        full_name: x.name.clone(),
+        description: x.description.clone(),
+        fork: x.fork,
+        archived: x.archived,
+        private: x.private,
+        default_branch: x.default_branch.clone(),
+        created_at: x.created_at.clone(),
+        pushed_at: x.pushed_at.clone(),
+        topics: x.topics.clone(),
+        watched: x.watched,
+        ..Default::default()
+      };
+
+      self.insert_repo(&repo).await?;
+      let query = sqlx::query(qs).bind(x.id).bind(x.hidden).bind(x.stars_synced);
+      let _ = query.bind(&x.first_seen).execute(&self.db).await?;
+    }
+
+    let qs = "
+    INSERT INTO repo_renames (name, repo_id) VALUES ($1, $2)
+    ON CONFLICT(name) DO UPDATE SET repo_id = excluded.repo_id;
+    ";
+
+    for x in &data.renames {
+      let _ = sqlx::query(qs).bind(&x.name).bind(x.repo_id).execute(&self.db).await?;
+    }
+
+    for x in &data.stats {
+      self.import_stats_row(x).await?;
+    }
+
+    // referrers / paths saved per repo & date, same as on sync
+    let mut referrers: HashMap<(i64, &str), Vec<RepoReferrer>> = HashMap::new();
+    for x in &data.referrers {
+      let rec =
+        RepoReferrer { referrer: x.name.clone(), count: x.count as u32, uniques: x.uniques as u32 };
+      referrers.entry((x.repo_id, x.date.as_str())).or_default().push(rec);
+    }
+
+    for ((repo_id, date), items) in referrers {
+      self
+        .insert_referrers(&Repo { id: repo_id as u64, ..Default::default() }, date, &items)
+        .await?;
+    }
+
+    let mut paths: HashMap<(i64, &str), Vec<RepoPopularPath>> = HashMap::new();
+    for x in &data.paths {
+      let rec = RepoPopularPath {
+        path: x.name.clone(),
+        title: x.title.clone().unwrap_or_default(),
+        count: x.count as u32,
+        uniques: x.uniques as u32,
+      };
+      paths.entry((x.repo_id, x.date.as_str())).or_default().push(rec);
+    }
+
+    for ((repo_id, date), items) in paths {
+      self.insert_paths(&Repo { id: repo_id as u64, ..Default::default() }, date, &items).await?;
+    }
+
+    Ok(())
+  }
+
+  /// Upsert daily stats lines (JSONL export) in single transaction, repos must already exist
+  pub async fn import_stats(&self, items: &[ExportStatsLine]) -> Res {
+    let ids = self.get_all_repos_ids().await?;
+    if let Some(x) = items.iter().find(|x| !ids.contains(&x.stats.repo_id)) {
+      anyhow::bail!("unknown repo {} (id {}), import full snapshot first", x.repo, x.stats.repo_id);
+    }
+
+    let tx = self.begin().await?;
+    let mut res = Ok(());
+    for x in items {
+      res = tx.import_stats_row(&x.stats).await;
+      if res.is_err() {
+        break;
+      }
+    }
+
+    tx.finish(res).await
+  }
+
+  /// Stats row saved with same inserts as on sync, so merged same way (greater value wins)
+  async fn import_stats_row(&self, x: &ExportStats) -> Res {
+    let repo = Repo {
+      id: x.repo_id as u64,
+      stargazers_count: x.stars as u32,
+      forks_count: x.forks as u32,
+      watchers_count: x.watchers as u32,
+      open_issues_count: (x.issues + x.prs) as u32,
+      size: x.size as u32,
+      ..Default::default()
+    };
+
+    // only count of pull requests is stored
+    let prs = (0..x.prs).map(|_| PullRequest { id: 0, title: String::new() }).collect();
+    let issues = IssueCounts { open: x.issues_open as u32, closed: x.issues_closed as u32 };
+    self.insert_stats(&repo, &x.date, &prs, Some(&issues)).await?;
+    self.insert_downloads(&repo, &x.date, x.downloads as u64).await?;
+
+    let daily = |count: i32, uniques: i32| TrafficDaily {
+      timestamp: x.date.clone(),
+      count: count as u32,
+      uniques: uniques as u32,
+    };
+
+    let clones = vec![daily(x.clones_count, x.clones_uniques)];
+    self.insert_clones(&repo, &RepoClones { uniques: 0, count: 0, clones }).await?;
+    let views = vec![daily(x.views_count, x.views_uniques)];
+    self.insert_views(&repo, &RepoViews { uniques: 0, count: 0, views }).await?;
+    Ok(())
+  }
+
+  /// Client on own single connection with open transaction, so `insert_*` calls made with it
+  /// are saved all together on `finish` (same way as migrations run on own connection)
+  async fn begin(&self) -> Res<DbClient> {
+    let opts = self.db.connect_options().as_ref().clone();
+    let db = SqlitePoolOptions::new().max_connections(1).connect_with(opts).await?;
+    sqlx::raw_sql("BEGIN IMMEDIATE;").execute(&db).await?;
+    Ok(DbClient { db, totals_window: self.totals_window, deltas_dates: self.deltas_dates })
+  }
+
+  /// Commit transaction opened by `begin` if `res` is ok, rollback otherwise
+  async fn finish(self, res: Res) -> Res {
+    let qs = if res.is_ok() { "COMMIT;" } else { "ROLLBACK;" };
+    sqlx::raw_sql(qs).execute(&self.db).await?;
+    self.db.close().await;
+    res
+  }
+
+  async fn get_all_repos_ids(&self) -> Res<HashSet<i64>> {
+    let items: Vec<(i64,)> = sqlx::query_as("SELECT id FROM repos;").fetch_all(&self.db).await?;
+    Ok(items.into_iter().map(|x| x.0).collect())
+  }
+
   // MARK: Inserters
 
   pub async fn insert_repo(&self, repo: &Repo) -> Res {
+    // repo id stays same on rename, so all stats kept – only old name is remembered
     let qs = "
-    INSERT INTO repos (id, name, description, archived, fork)
-    VALUES ($1, $2, $3, $4, $5)
-    ON CONFLICT(id) DO UPDATE SET
+    INSERT INTO repo_renames (name, repo_id)
+    SELECT name, id FROM repos WHERE id = $1 AND name != $2
+    ON CONFLICT(name) DO UPDATE SET repo_id = excluded.repo_id;
+    ";
+
+    let _ = sqlx::query(qs).bind(repo.id as i64).bind(&repo.full_name).execute(&self.db).await?;
+
+    let qs = "
+    INSERT INTO repos
+      (id, name, description, archived, fork, default_branch, private, created_at, pushed_at, watched, first_seen)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, strftime('%Y-%m-%dT00:00:00Z', 'now'))
+    ON CONFLICT(id) DO UPDATE SET -- first_seen set once on first insert
+      first_seen = COALESCE(repos.first_seen, excluded.first_seen),
       name = excluded.name,
       description = excluded.description,
       archived = excluded.archived,
       fork = excluded.fork,
+      default_branch = excluded.default_branch,
+      private = excluded.private,
+      created_at = excluded.created_at,
+      pushed_at = excluded.pushed_at,
+      watched = excluded.watched,
       hidden = FALSE; -- reset hidden flag if repo was hidden and appeared again
     ";
 
@@ -419,22 +1633,59 @@ impl DbClient {
       .bind(&repo.description)
       .bind(repo.archived)
       .bind(repo.fork)
+      .bind(&repo.default_branch)
+      .bind(repo.private)
+      .bind(&repo.created_at)
+      .bind(&repo.pushed_at)
+      .bind(repo.watched)
       .execute(&self.db)
       .await?;
 
+    // topics are replaced as whole, so removed on GitHub are removed here too
+    let qs = "DELETE FROM repo_topics WHERE repo_id = $1;";
+    let _ = sqlx::query(qs).bind(repo.id as i64).execute(&self.db).await?;
+
+    for topic in &repo.topics {
+      let qs = "INSERT OR IGNORE INTO repo_topics (repo_id, topic) VALUES ($1, $2);";
+      let _ = sqlx::query(qs).bind(repo.id as i64).bind(topic).execute(&self.db).await?;
+    }
+
     Ok(())
   }
 
-  pub async fn insert_stats(&self, repo: &Repo, date: &str, prs: &Vec<PullRequest>) -> Res {
+  pub async fn insert_stats(
+    &self,
+    repo: &Repo,
+    date: &str,
+    prs: &Vec<PullRequest>,
+    issues: Option<&IssueCounts>,
+  ) -> Res {
+    // unknown issues count (graphql failed) is not stored as zero: day row keeps its value,
+    // new day row takes last known one
     let qs = "
-    INSERT INTO repo_stats AS t (repo_id, date, stars, forks, watchers, issues, prs)
-    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    INSERT INTO repo_stats AS t
+      (repo_id, date, stars, forks, watchers, issues, prs, issues_open, issues_closed, size)
+    VALUES ($1, $2, $3, $4, $5, $6, $7,
+      COALESCE($8, (
+        SELECT issues_open FROM repo_stats WHERE repo_id = $1 AND date < $2
+        ORDER BY date DESC LIMIT 1
+      ), 0),
+      COALESCE($9, (
+        SELECT issues_closed FROM repo_stats WHERE repo_id = $1 AND date < $2
+        ORDER BY date DESC LIMIT 1
+      ), 0),
+      $10)
     ON CONFLICT(repo_id, date) DO UPDATE SET
       stars = MAX(t.stars, excluded.stars),
       forks = MAX(t.forks, excluded.forks),
       watchers = MAX(t.watchers, excluded.watchers),
       issues = MAX(t.issues, excluded.issues),
-      prs = MAX(t.prs, excluded.prs);
+      prs = MAX(t.prs, excluded.prs),
+      issues_open = CASE WHEN $8 IS NULL THEN t.issues_open
+        ELSE MAX(t.issues_open, excluded.issues_open) END,
+      issues_closed = CASE WHEN $9 IS NULL THEN t.issues_closed
+        ELSE MAX(t.issues_closed, excluded.issues_closed) END,
+      size = MAX(t.size, excluded.size);
     ";
 
     let _ = sqlx::query(qs)
@@ -445,6 +1696,28 @@ impl DbClient {
       .bind(repo.watchers_count as i32)
       .bind(repo.open_issues_count as i32 - prs.len() as i32)
       .bind(prs.len() as i32)
+      .bind(issues.map(|x| x.open as i32))
+      .bind(issues.map(|x| x.closed as i32))
+      .bind(repo.size as i32)
+      .execute(&self.db)
+      .await?;
+
+    Ok(())
+  }
+
+  /// Save total release downloads, stored only if greater than already saved for this day
+  pub async fn insert_downloads(&self, repo: &Repo, date: &str, downloads: u64) -> Res {
+    let qs = "
+    INSERT INTO repo_stats AS t (repo_id, date, downloads)
+    VALUES ($1, $2, $3)
+    ON CONFLICT(repo_id, date) DO UPDATE SET
+      downloads = MAX(t.downloads, excluded.downloads);
+    ";
+
+    let _ = sqlx::query(qs)
+      .bind(repo.id as i64)
+      .bind(date)
+      .bind(downloads.min(i32::MAX as u64) as i32)
       .execute(&self.db)
       .await?;
 
@@ -524,13 +1797,27 @@ impl DbClient {
       uniques = MAX(t.uniques, excluded.uniques);
     ";
 
+    // same referrer can be reported in different case or with trailing slash
+    let mut merged: Vec<(String, u32, u32)> = Vec::with_capacity(docs.len());
     for rec in docs {
+      let name = rec.referrer.trim().trim_end_matches('/').to_lowercase();
+      match merged.iter_mut().find(|x| x.0 == name) {
+        // same visitors can be counted in both variants, so uniques are not summed
+        Some(x) => {
+          x.1 += rec.count;
+          x.2 = x.2.max(rec.uniques);
+        }
+        None => merged.push((name, rec.count, rec.uniques)),
+      }
+    }
+
+    for (referrer, count, uniques) in merged {
       let _ = sqlx::query(qs)
         .bind(repo.id as i64)
         .bind(&date)
-        .bind(&rec.referrer)
-        .bind(rec.count as i32)
-        .bind(rec.uniques as i32)
+        .bind(&referrer)
+        .bind(count as i32)
+        .bind(uniques as i32)
         .execute(&self.db)
         .await?;
     }
@@ -564,29 +1851,30 @@ impl DbClient {
 
   // MARK: Updater
 
+  /// Update referrers / paths deltas for last `GHS_DELTAS_DATES` dates (all if not set)
   pub async fn update_deltas(&self) -> Res {
+    self.update_deltas_for(self.deltas_dates).await
+  }
+
+  /// Update referrers / paths deltas over full history
+  pub async fn update_deltas_full(&self) -> Res {
+    self.update_deltas_for(0).await
+  }
+
+  async fn update_deltas_for(&self, dates: i32) -> Res {
+    let stime = std::time::Instant::now();
     let items = [("repo_referrers", "referrer"), ("repo_popular_paths", "path")];
 
     for (table, col) in items {
-      #[rustfmt::skip]
-      let qs = format!("
-      WITH cte AS (
-      SELECT
-        rr.repo_id, rr.date, rr.{col}, rr.uniques, rr.count,
-        LAG(rr.uniques) OVER (PARTITION BY rr.repo_id, rr.{col} ORDER BY rr.date) AS prev_uniques,
-        LAG(rr.count) OVER (PARTITION BY rr.repo_id, rr.{col} ORDER BY rr.date) AS prev_count
-      FROM {table} rr
-      )
-      UPDATE {table} AS rr	SET
-        uniques_delta = MAX(0, cte.uniques - COALESCE(cte.prev_uniques, 0)),
-        count_delta = MAX(0, cte.count - COALESCE(cte.prev_count, 0))
-      FROM cte
-      WHERE rr.repo_id = cte.repo_id AND rr.date = cte.date AND rr.{col} = cte.{col};
-      ");
-
+      let qs = deltas_query(table, col, dates);
       let _ = sqlx::query(qs.as_str()).execute(&self.db).await?;
     }
 
+    let mode = match dates {
+      x if x > 0 => format!("last {} dates", x),
+      _ => "all dates".to_string(),
+    };
+    tracing::info!("update_deltas took {:?} ({})", stime.elapsed(), mode);
     Ok(())
   }
 
@@ -603,3 +1891,821 @@ impl DbClient {
     Ok(())
   }
 }
+
+/// Temporary database for tests, file removed on drop (also if test panics)
+#[cfg(test)]
+pub(crate) struct TestDb {
+  pub client: DbClient,
+  pub path: std::path::PathBuf,
+}
+
+#[cfg(test)]
+impl TestDb {
+  pub async fn new() -> Self {
+    // tests run in parallel, so each one gets own file
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let idx = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let name = format!("ghstats-test-{}-{}.db", std::process::id(), idx);
+    let path = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_file(&path); // leftover of killed run with same pid
+    let client = DbClient::new(path.to_str().unwrap()).await.unwrap();
+    Self { client, path }
+  }
+}
+
+#[cfg(test)]
+impl TestDb {
+  /// Save repo with its stats of given day (no issues & PRs)
+  pub async fn seed_repo(&self, repo: &Repo, date: &str) {
+    self.insert_repo(repo).await.unwrap();
+    self.insert_stats(repo, date, &vec![], Some(&IssueCounts::default())).await.unwrap();
+  }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDb {
+  type Target = DbClient;
+
+  fn deref(&self) -> &Self::Target {
+    &self.client
+  }
+}
+
+#[cfg(test)]
+impl Drop for TestDb {
+  fn drop(&mut self) {
+    for ext in ["", "-wal", "-shm"] {
+      let _ = std::fs::remove_file(format!("{}{}", self.path.display(), ext));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  async fn test_db() -> TestDb {
+    TestDb::new().await
+  }
+
+  fn test_repo(id: u64, name: &str) -> Repo {
+    Repo { id, full_name: name.to_string(), ..Default::default() }
+  }
+
+  #[tokio::test]
+  async fn test_repo_rename() {
+    let db = test_db().await;
+    let date = "2024-01-01T00:00:00Z";
+
+    for name in ["foo/old", "foo/mid", "foo/new"] {
+      let repo = test_repo(1, name);
+      db.seed_repo(&repo, date).await;
+    }
+
+    assert_eq!(db.get_repos_ids().await.unwrap(), vec![1]);
+    assert!(db.get_repo_totals("foo/old").await.unwrap().is_none());
+    assert!(db.get_repo_totals("foo/mid").await.unwrap().is_none());
+    assert_eq!(db.get_repo_totals("foo/new").await.unwrap().unwrap().id, 1);
+
+    assert_eq!(db.get_renamed_repo("foo/old").await.unwrap(), Some("foo/new".to_string()));
+    assert_eq!(db.get_renamed_repo("foo/mid").await.unwrap(), Some("foo/new".to_string()));
+    assert_eq!(db.get_renamed_repo("foo/new").await.unwrap(), None);
+    assert_eq!(db.get_renamed_repo("foo/abc").await.unwrap(), None);
+
+    // renamed back to old name
+    db.insert_repo(&test_repo(1, "foo/old")).await.unwrap();
+    assert_eq!(db.get_renamed_repo("foo/old").await.unwrap(), None);
+    assert_eq!(db.get_renamed_repo("foo/new").await.unwrap(), Some("foo/old".to_string()));
+  }
+
+  #[tokio::test]
+  async fn test_private_aliases() {
+    let db = test_db().await;
+    for (id, name, private) in [(3, "foo/c", true), (1, "foo/a", true), (2, "foo/b", false)] {
+      db.insert_repo(&Repo { private, ..test_repo(id, name) }).await.unwrap();
+    }
+
+    let items = db.get_private_aliases().await.unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items.get(&1).map(|x| x.as_str()), Some("private-repo-1"));
+    assert_eq!(items.get(&3).map(|x| x.as_str()), Some("private-repo-2"));
+    assert_eq!(items.get(&2), None);
+
+    let name = |alias: &'static str| db.get_repo_by_alias(alias);
+    assert_eq!(name("private-repo-2").await.unwrap(), Some("foo/c".to_string()));
+    assert_eq!(name("private-repo-3").await.unwrap(), None);
+    assert_eq!(name("private-repo-0").await.unwrap(), None);
+    assert_eq!(name("foo").await.unwrap(), None);
+  }
+
+  #[tokio::test]
+  async fn test_referrers_normalized() {
+    let db = test_db().await;
+    let date = "2024-01-01T00:00:00Z";
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    let refs = vec![
+      RepoReferrer { referrer: "Google".to_string(), count: 10, uniques: 5 },
+      RepoReferrer { referrer: "google/".to_string(), count: 3, uniques: 2 },
+      RepoReferrer { referrer: "github.com".to_string(), count: 1, uniques: 1 },
+    ];
+    db.insert_referrers(&repo, date, &refs).await.unwrap();
+    db.update_deltas().await.unwrap();
+
+    let filter = PopularFilter::default();
+    let items = db.get_popular_items("foo/bar", &PopularKind::Refs, &filter).await.unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].name, "google");
+    assert_eq!((items[0].count, items[0].uniques), (13, 5));
+    assert_eq!(items[1].name, "github.com");
+  }
+
+  #[tokio::test]
+  async fn test_referrers_migration() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    // rows stored before normalization
+    let qs = "INSERT INTO repo_referrers (repo_id, date, referrer, count, uniques) VALUES
+      (1, '2024-01-01', 'Google', 10, 5), (1, '2024-01-01', 'google/', 3, 2),
+      (1, '2024-01-02', 'google', 15, 6);";
+    sqlx::query(qs).execute(&db.db).await.unwrap();
+    migrate_v14(&db.db).await.unwrap();
+
+    let refs = db.export_data().await.unwrap().referrers;
+    let refs = refs.iter().map(|x| (x.name.as_str(), x.count, x.uniques)).collect::<Vec<_>>();
+    assert_eq!(refs, vec![("google", 13, 5), ("google", 15, 6)]);
+
+    let filter = PopularFilter::default();
+    let items = db.get_popular_items("foo/bar", &PopularKind::Refs, &filter).await.unwrap();
+    assert_eq!((items.len(), items[0].count, items[0].uniques), (1, 15, 6));
+  }
+
+  #[tokio::test]
+  async fn test_repos_sort_by_name() {
+    let db = test_db().await;
+    let date = "2024-01-01T00:00:00Z";
+
+    for (id, name) in [(1, "foo/Zebra"), (2, "foo/apple"), (3, "foo/Mango")] {
+      let repo = test_repo(id, name);
+      db.seed_repo(&repo, date).await;
+    }
+
+    let filter =
+      RepoFilter { sort: RepoSort::Name, direction: Direction::Asc, ..Default::default() };
+    let names =
+      db.get_repos(&filter).await.unwrap().into_iter().map(|x| x.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["foo/apple", "foo/Mango", "foo/Zebra"]);
+
+    let filter =
+      RepoFilter { sort: RepoSort::Name, direction: Direction::Desc, ..Default::default() };
+    let names =
+      db.get_repos(&filter).await.unwrap().into_iter().map(|x| x.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["foo/Zebra", "foo/Mango", "foo/apple"]);
+  }
+
+  #[tokio::test]
+  async fn test_forks_gaps_restored() {
+    let db = test_db().await;
+    let dates = ["2024-01-01", "2024-01-03", "2024-01-04"];
+
+    for (date, forks) in dates.iter().zip([3, 0, 5]) {
+      let repo = Repo { forks_count: forks, ..test_repo(1, "foo/bar") };
+      db.seed_repo(&repo, date).await;
+    }
+
+    let forks = db.get_forks("foo/bar", -1).await.unwrap();
+    // only day without row filled, zero in row kept
+    assert_eq!(forks.iter().map(|x| x.forks).collect::<Vec<_>>(), vec![3, 3, 0, 5]);
+    assert_eq!(forks[1].date, "2024-01-02T00:00:00Z");
+  }
+
+  #[tokio::test]
+  async fn test_issues_unknown_count() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    let issues = IssueCounts { open: 5, closed: 2 };
+    db.insert_stats(&repo, "2024-01-01T00:00:00Z", &vec![], Some(&issues)).await.unwrap();
+    db.insert_stats(&repo, "2024-01-01T00:00:00Z", &vec![], None).await.unwrap();
+    let totals = db.get_repo_totals("foo/bar").await.unwrap().unwrap();
+    assert_eq!((totals.issues_open, totals.issues_closed), (5, 2));
+
+    // new day without count takes last known one, not zero
+    db.insert_stats(&repo, "2024-01-02T00:00:00Z", &vec![], None).await.unwrap();
+    let totals = db.get_repo_totals("foo/bar").await.unwrap().unwrap();
+    assert_eq!(totals.date, "2024-01-02T00:00:00Z");
+    assert_eq!((totals.issues_open, totals.issues_closed), (5, 2));
+  }
+
+  #[tokio::test]
+  async fn test_issues_history() {
+    let db = test_db().await;
+    let dates = ["2024-01-01", "2024-01-02", "2024-01-03"];
+
+    // open_issues_count from GitHub includes PRs, history stores issues only
+    for (date, count) in dates.iter().zip([4, 6, 9]) {
+      let repo = Repo { open_issues_count: count, ..test_repo(1, "foo/bar") };
+      let prs = vec![PullRequest { id: 1, title: "pr".to_string() }];
+      db.insert_repo(&repo).await.unwrap();
+      db.insert_stats(&repo, date, &prs, Some(&IssueCounts::default())).await.unwrap();
+    }
+
+    let issues = db.get_issues_history("foo/bar", -1).await.unwrap();
+    assert_eq!(issues.iter().map(|x| x.issues).collect::<Vec<_>>(), vec![3, 5, 8]);
+  }
+
+  #[tokio::test]
+  async fn test_daily_delta() {
+    let db = test_db().await;
+
+    // first day, no previous row
+    let repo = Repo { stargazers_count: 10, ..test_repo(1, "foo/bar") };
+    db.seed_repo(&repo, "2024-01-01T00:00:00Z").await;
+    let delta = db.get_daily_delta("foo/bar").await.unwrap().unwrap();
+    assert_eq!(delta.stars, 0);
+
+    let repo = Repo { stargazers_count: 13, ..test_repo(1, "foo/bar") };
+    db.seed_repo(&repo, "2024-01-02T00:00:00Z").await;
+    let delta = db.get_daily_delta("foo/bar").await.unwrap().unwrap();
+    assert_eq!((delta.date.as_str(), delta.stars), ("2024-01-02T00:00:00Z", 3));
+
+    let deltas = db.get_daily_deltas().await.unwrap();
+    assert_eq!(deltas.get(&1).unwrap().stars, 3);
+    assert!(db.get_daily_delta("foo/abc").await.unwrap().is_none());
+  }
+
+  #[tokio::test]
+  async fn test_repos_sort_by_uniques() {
+    let db = test_db().await;
+    let date = "2024-01-01T00:00:00Z";
+
+    // more views, but less unique visitors
+    let items = [(1, "foo/many", 100, 5), (2, "foo/unique", 50, 40)];
+    for (id, name, count, uniques) in items {
+      let repo = test_repo(id, name);
+      db.seed_repo(&repo, date).await;
+      let daily = crate::gh_client::TrafficDaily { timestamp: date.to_string(), uniques, count };
+      let views = RepoViews { uniques, count, views: vec![daily] };
+      db.insert_views(&repo, &views).await.unwrap();
+    }
+
+    let db = &db;
+    let names = |sort| async move {
+      let filter = RepoFilter { sort, direction: Direction::Desc, ..Default::default() };
+      let repos = db.get_repos(&filter).await.unwrap();
+      repos.into_iter().map(|x| x.name).collect::<Vec<_>>()
+    };
+
+    assert_eq!(names(RepoSort::Views).await, vec!["foo/many", "foo/unique"]);
+    assert_eq!(names(RepoSort::ViewsUniques).await, vec!["foo/unique", "foo/many"]);
+    assert_eq!(RepoSort::ViewsUniques.to_string(), "views_uniques");
+  }
+
+  #[tokio::test]
+  async fn test_repos_active_filter() {
+    let db = test_db().await;
+    let date = "2024-01-01T00:00:00Z";
+
+    for (id, name) in [(1, "foo/idle"), (2, "foo/busy")] {
+      let repo = test_repo(id, name);
+      db.seed_repo(&repo, date).await;
+    }
+
+    let daily =
+      crate::gh_client::TrafficDaily { timestamp: date.to_string(), uniques: 1, count: 2 };
+    let views = RepoViews { uniques: 1, count: 2, views: vec![daily] };
+    db.insert_views(&test_repo(2, "foo/busy"), &views).await.unwrap();
+
+    let filter = RepoFilter { active: Some(true), ..Default::default() };
+    let names =
+      db.get_repos(&filter).await.unwrap().into_iter().map(|x| x.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["foo/busy"]);
+
+    let filter = RepoFilter { active: Some(false), ..Default::default() };
+    assert_eq!(db.get_repos(&filter).await.unwrap().len(), 2);
+  }
+
+  #[tokio::test]
+  async fn test_repos_show_hidden() {
+    let db = test_db().await;
+    let date = "2024-01-01T00:00:00Z";
+
+    for (id, name) in [(1, "foo/bar"), (2, "foo/gone")] {
+      let repo = test_repo(id, name);
+      db.seed_repo(&repo, date).await;
+    }
+    db.mark_repo_hidden(&vec![2]).await.unwrap();
+
+    assert_eq!(db.get_repos(&RepoFilter::default()).await.unwrap().len(), 1);
+    assert!(db.get_repo_totals("foo/gone").await.unwrap().is_none());
+
+    let filter = RepoFilter { show_hidden: true, ..Default::default() };
+    let repos = db.get_repos(&filter).await.unwrap();
+    assert_eq!(repos.len(), 2);
+    assert!(repos.iter().any(|x| x.name == "foo/gone" && x.hidden));
+  }
+
+  #[tokio::test]
+  async fn test_tracking_summary() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    // stars history before tracking started should not move start date
+    let stars = vec![("2023-06-01T00:00:00Z".to_string(), 5, 5)];
+    db.insert_stars(1, &stars).await.unwrap();
+    assert!(db.get_tracking_summary("foo/bar").await.unwrap().is_none());
+
+    let days = ["2024-01-01T00:00:00Z", "2024-01-03T00:00:00Z"];
+    let views = days.iter().map(|x| crate::gh_client::TrafficDaily {
+      timestamp: x.to_string(),
+      uniques: 1,
+      count: 2,
+    });
+    let views = RepoViews { uniques: 2, count: 4, views: views.collect() };
+    db.insert_views(&repo, &views).await.unwrap();
+
+    let repo = Repo { stargazers_count: 8, ..test_repo(1, "foo/bar") };
+    db.seed_repo(&repo, days[1]).await;
+
+    let summary = db.get_tracking_summary("foo/bar").await.unwrap().unwrap();
+    assert_eq!(summary.since, days[0]);
+    assert_eq!(summary.days, 3);
+    assert_eq!(summary.views_count, 4);
+    assert_eq!(summary.stars, 3); // compared to last known value before tracking started
+  }
+
+  #[tokio::test]
+  async fn test_new_stars() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    let day = |ago: i64| {
+      let date = chrono::Utc::now() - chrono::Duration::days(ago);
+      date.format("%Y-%m-%dT00:00:00Z").to_string()
+    };
+
+    let stars = vec![(day(60), 4, 4), (day(40), 10, 6), (day(10), 12, 2), (day(0), 15, 3)];
+    db.insert_stars(1, &stars).await.unwrap();
+
+    assert_eq!(db.get_new_stars("foo/bar", 30).await.unwrap(), 5);
+    assert_eq!(db.get_new_stars("foo/bar", 50).await.unwrap(), 11);
+    assert_eq!(db.get_new_stars("foo/bar", 90).await.unwrap(), 15);
+
+    let filter = RepoFilter { sort: RepoSort::StarsNew, ..Default::default() };
+    let repos = db.get_repos(&filter).await.unwrap();
+    assert_eq!(repos[0].stars_new, 5);
+  }
+
+  #[tokio::test]
+  async fn test_export_stats_pages() {
+    let db = test_db().await;
+    for (id, name) in [(1, "foo/bar"), (2, "foo/baz")] {
+      let repo = test_repo(id, name);
+      for date in ["2024-01-01T00:00:00Z", "2024-01-02T00:00:00Z", "2024-01-03T00:00:00Z"] {
+        db.seed_repo(&repo, date).await;
+      }
+    }
+
+    let mut after = None;
+    let mut rows = vec![];
+    loop {
+      let items = db.export_stats_page(after.as_ref(), 2).await.unwrap();
+      if items.is_empty() {
+        break;
+      }
+      after = items.last().map(|x| (x.stats.repo_id, x.stats.date.clone()));
+      rows.extend(items);
+    }
+
+    assert_eq!(rows.len(), 6);
+    assert_eq!(rows[0].repo, "foo/bar");
+    assert_eq!(rows[3].repo, "foo/baz");
+    assert_eq!(rows[5].stats.date, "2024-01-03T00:00:00Z");
+  }
+
+  #[tokio::test]
+  async fn test_repo_topics() {
+    let db = test_db().await;
+    let date = "2024-01-01T00:00:00Z";
+
+    for (id, name, topics) in [(1, "foo/bar", vec!["cli", "rust"]), (2, "foo/baz", vec!["web"])] {
+      let topics = topics.into_iter().map(|x| x.to_string()).collect();
+      let repo = Repo { topics, ..test_repo(id, name) };
+      db.seed_repo(&repo, date).await;
+    }
+
+    assert_eq!(db.get_repo_topics("foo/bar").await.unwrap(), vec!["cli", "rust"]);
+
+    let filter = RepoFilter { topic: Some("CLI".to_string()), ..Default::default() };
+    let repos = db.get_repos(&filter).await.unwrap();
+    assert_eq!(repos.iter().map(|x| x.name.as_str()).collect::<Vec<_>>(), vec!["foo/bar"]);
+
+    // topics replaced on update
+    let repo = Repo { topics: vec!["web".to_string()], ..test_repo(1, "foo/bar") };
+    db.insert_repo(&repo).await.unwrap();
+    assert_eq!(db.get_repo_topics("foo/bar").await.unwrap(), vec!["web"]);
+
+    let filter = RepoFilter { topic: Some("web".to_string()), ..Default::default() };
+    assert_eq!(db.get_repos(&filter).await.unwrap().len(), 2);
+  }
+
+  #[tokio::test]
+  async fn test_traffic_spikes() {
+    let db = test_db().await;
+
+    for (id, name, latest) in [(1, "foo/calm", 12), (2, "foo/hype", 100)] {
+      let repo = test_repo(id, name);
+      let mut daily = vec![];
+      for (idx, count) in [10, 12, 8, 10, latest].into_iter().enumerate() {
+        let date = format!("2024-01-0{}T00:00:00Z", idx + 1);
+        db.seed_repo(&repo, &date).await;
+        daily.push(crate::gh_client::TrafficDaily { timestamp: date, uniques: 1, count });
+      }
+
+      let views = RepoViews { uniques: 1, count: 1, views: daily };
+      db.insert_views(&repo, &views).await.unwrap();
+    }
+
+    let spikes = db.get_traffic_spikes(3.0, 7, 10).await.unwrap();
+    assert_eq!(spikes.len(), 1);
+    assert_eq!((spikes[0].name.as_str(), spikes[0].views_count), ("foo/hype", 100));
+    assert_eq!(spikes[0].avg_views, 10.0);
+
+    // below minimum views not reported
+    assert!(db.get_traffic_spikes(3.0, 7, 200).await.unwrap().is_empty());
+
+    // days out of window not averaged, even if no rows between
+    let repo = test_repo(3, "foo/stale");
+    let mut daily = vec![];
+    for (date, count) in [("2023-12-01T00:00:00Z", 1), ("2024-01-05T00:00:00Z", 50)] {
+      db.seed_repo(&repo, date).await;
+      daily.push(crate::gh_client::TrafficDaily { timestamp: date.to_string(), uniques: 1, count });
+    }
+    db.insert_views(&repo, &RepoViews { uniques: 1, count: 1, views: daily }).await.unwrap();
+    assert_eq!(db.get_traffic_spikes(3.0, 7, 10).await.unwrap().len(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_aggregate_stars() {
+    let db = test_db().await;
+    for (id, name) in [(1, "foo/old"), (2, "foo/new")] {
+      db.insert_repo(&test_repo(id, name)).await.unwrap();
+    }
+
+    let day = |x: u32| format!("2024-01-{:02}T00:00:00Z", x);
+    db.insert_stars(1, &vec![(day(1), 5, 5), (day(3), 8, 3), (day(6), 9, 1)]).await.unwrap();
+    db.insert_stars(2, &vec![(day(3), 2, 2), (day(4), 4, 2)]).await.unwrap();
+
+    // gap row without stars (e.g. only traffic collected) does not reset repo value
+    let repo = test_repo(2, "foo/new");
+    db.seed_repo(&repo, &day(5)).await;
+
+    let stars = db.get_aggregate_stars(&[1, 2]).await.unwrap();
+    let stars = stars.into_iter().map(|x| (x.date, x.stars)).collect::<Vec<_>>();
+    assert_eq!(stars, vec![(day(1), 5), (day(3), 10), (day(4), 12), (day(6), 13)]);
+
+    assert!(db.get_aggregate_stars(&[]).await.unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_repo_first_seen() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.seed_repo(&repo, "2024-01-01T00:00:00Z").await;
+
+    let today = chrono::Utc::now().format("%Y-%m-%dT00:00:00Z").to_string();
+    let totals = db.get_repo_totals("foo/bar").await.unwrap().unwrap();
+    assert_eq!(totals.first_seen.as_ref(), Some(&today));
+
+    // not overwritten on next sync
+    let qs = "UPDATE repos SET first_seen = '2023-05-01T00:00:00Z' WHERE id = 1;";
+    sqlx::query(qs).execute(&db.db).await.unwrap();
+    db.insert_repo(&Repo { description: Some("new".to_string()), ..repo }).await.unwrap();
+
+    let totals = db.get_repo_totals("foo/bar").await.unwrap().unwrap();
+    assert_eq!(totals.first_seen.as_deref(), Some("2023-05-01T00:00:00Z"));
+  }
+
+  #[tokio::test]
+  async fn test_popular_exclude_root() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    let item = |path: &str, count| RepoPopularPath {
+      path: path.to_string(),
+      title: "".to_string(),
+      count,
+      uniques: 1,
+    };
+    let paths = vec![item("/foo/bar", 10), item("/foo/bar/issues", 2)];
+    db.insert_paths(&repo, "2024-01-01T00:00:00Z", &paths).await.unwrap();
+    db.update_deltas_full().await.unwrap();
+
+    let db = &db;
+    let names = |exclude_root| async move {
+      let filter = PopularFilter { exclude_root, ..Default::default() };
+      let items = db.get_popular_items("foo/bar", &PopularKind::Path, &filter).await.unwrap();
+      items.into_iter().map(|x| x.name).collect::<Vec<_>>()
+    };
+
+    assert_eq!(names(false).await.len(), 2);
+    assert_eq!(names(true).await, vec!["/foo/bar/issues"]);
+  }
+
+  #[tokio::test]
+  async fn test_window_metrics() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    let day = |x: i64| {
+      let date = chrono::Utc::now() - chrono::Duration::days(x);
+      date.format("%Y-%m-%dT00:00:00Z").to_string()
+    };
+
+    let daily =
+      |x: i64, count| crate::gh_client::TrafficDaily { timestamp: day(x), uniques: 1, count };
+    let views = vec![daily(30, 100), daily(14, 1000), daily(13, 10), daily(1, 1)];
+    let views = RepoViews { uniques: 4, count: 1111, views };
+    db.insert_views(&repo, &views).await.unwrap();
+
+    // 14 days window is today and 13 days before
+    let items = db.get_window_metrics("foo/bar", 14).await.unwrap();
+    assert_eq!((items.views_count, items.views_uniques), (11, 2));
+    assert_eq!(items.date, day(1));
+
+    let items = db.get_window_metrics("foo/none", 14).await.unwrap();
+    assert_eq!((items.views_count, items.date.as_str()), (0, ""));
+  }
+
+  #[tokio::test]
+  async fn test_weekday_distribution() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    // no data yet
+    let items = db.get_weekday_distribution("foo/bar").await.unwrap();
+    assert_eq!(items.len(), 7);
+    assert!(items.iter().all(|x| x.days == 0 && x.views_count == 0));
+
+    // stars before traffic collection started are not counted
+    db.insert_stars(1, &vec![("2024-01-01T00:00:00Z".to_string(), 1, 1)]).await.unwrap();
+
+    // 2024-03-04 is Monday, 2024-03-11 is next Monday, 2024-03-06 is Wednesday
+    let daily = |ts: &str, count| crate::gh_client::TrafficDaily {
+      timestamp: ts.to_string(),
+      uniques: 1,
+      count,
+    };
+    let views = RepoViews {
+      uniques: 3,
+      count: 35,
+      views: vec![
+        daily("2024-03-04T00:00:00Z", 10),
+        daily("2024-03-06T00:00:00Z", 5),
+        daily("2024-03-11T00:00:00Z", 20),
+      ],
+    };
+    db.insert_views(&repo, &views).await.unwrap();
+
+    let items = db.get_weekday_distribution("foo/bar").await.unwrap();
+    let items = items.iter().map(|x| (x.weekday, x.days, x.views_count)).collect::<Vec<_>>();
+    assert_eq!(
+      items,
+      vec![(0, 0, 0), (1, 2, 30), (2, 0, 0), (3, 1, 5), (4, 0, 0), (5, 0, 0), (6, 0, 0)]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_recent_stars_all() {
+    let db = test_db().await;
+    for (id, name) in [(1, "foo/synced"), (2, "foo/pending")] {
+      db.insert_repo(&test_repo(id, name)).await.unwrap();
+    }
+
+    let day = |x: i64| {
+      let date = chrono::Utc::now() - chrono::Duration::days(x);
+      date.format("%Y-%m-%dT00:00:00Z").to_string()
+    };
+
+    db.insert_stars(1, &vec![(day(40), 1, 1), (day(5), 3, 2), (day(3), 6, 3)]).await.unwrap();
+    db.insert_stars(2, &vec![(day(5), 2, 2)]).await.unwrap();
+    db.mark_repo_stars_synced(1).await.unwrap();
+
+    // gap row without stars keeps previous value
+    let repo = test_repo(1, "foo/synced");
+    db.seed_repo(&repo, &day(1)).await;
+
+    let items = db.get_recent_stars_all(30).await.unwrap();
+    assert_eq!(items.get(&1), Some(&vec![3, 6, 6]));
+    assert_eq!(items.get(&2), None); // stars not synced yet
+  }
+
+  #[tokio::test]
+  async fn test_repos_owner_filter() {
+    let db = test_db().await;
+    let date = "2024-01-01T00:00:00Z";
+
+    for (id, name) in [(1, "foo/bar"), (2, "Foo/baz"), (3, "foobar/abc"), (4, "f_o/abc")] {
+      let repo = test_repo(id, name);
+      db.seed_repo(&repo, date).await;
+    }
+
+    let db = &db;
+    let names = |owner: &str| {
+      let filter = RepoFilter { owner: Some(owner.to_string()), ..Default::default() };
+      async move {
+        let repos = db.get_repos(&filter).await.unwrap();
+        let mut names = repos.into_iter().map(|x| x.name).collect::<Vec<_>>();
+        names.sort();
+        names
+      }
+    };
+
+    assert_eq!(names("foo").await, vec!["Foo/baz", "foo/bar"]);
+    assert_eq!(names("foobar").await, vec!["foobar/abc"]);
+    assert_eq!(names("f_o").await, vec!["f_o/abc"]); // `_` is not wildcard
+    assert_eq!(names("").await.len(), 4);
+
+    let filter = RepoFilter {
+      owner: Some("foo".to_string()),
+      topic: Some("cli".to_string()),
+      ..Default::default()
+    };
+    assert!(db.get_repos(&filter).await.unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_deltas_incremental() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+    db.insert_repo(&repo).await.unwrap();
+
+    let day = |x: u32| format!("2024-01-{:02}T00:00:00Z", x);
+    let refs = |items: &[(&str, u32)]| {
+      let items = items.iter().map(|(name, count)| RepoReferrer {
+        referrer: name.to_string(),
+        count: *count,
+        uniques: 1,
+      });
+      items.collect::<Vec<_>>()
+    };
+
+    let history = vec![
+      (1, refs(&[("google", 5), ("github.com", 2)])),
+      (2, refs(&[("google", 8)])),
+      (3, refs(&[("google", 9), ("github.com", 6)])), // github.com absent on previous date
+    ];
+
+    let deltas = |db: &DbClient| {
+      let qs = "SELECT date, referrer, count_delta FROM repo_referrers ORDER BY date, referrer;";
+      let db = db.db.clone();
+      async move { sqlx::query_as::<_, (String, String, i32)>(qs).fetch_all(&db).await.unwrap() }
+    };
+
+    for (x, items) in &history {
+      db.insert_referrers(&repo, &day(*x), items).await.unwrap();
+    }
+    db.update_deltas_full().await.unwrap();
+    let full = deltas(&db).await;
+    assert_eq!(full[3], (day(3), "github.com".to_string(), 4));
+
+    // reset last date and new date added between runs, only last 2 dates recomputed
+    let qs = "UPDATE repo_referrers SET count_delta = 0 WHERE date >= $1;";
+    sqlx::query(qs).bind(day(3)).execute(&db.db).await.unwrap();
+    db.insert_referrers(&repo, &day(4), &refs(&[("google", 12)])).await.unwrap();
+    db.update_deltas_for(2).await.unwrap();
+
+    let incremental = deltas(&db).await;
+    db.update_deltas_full().await.unwrap();
+    assert_eq!(incremental, deltas(&db).await);
+    assert_eq!(incremental.last().unwrap(), &(day(4), "google".to_string(), 3));
+  }
+
+  // cargo test --release bench_deltas_large -- --ignored
+  #[tokio::test]
+  #[ignore]
+  async fn bench_deltas_large() {
+    let db = test_db().await;
+
+    // 20 repos x 20 referrers x 365 dates
+    let qs = "
+    WITH RECURSIVE n(x) AS (SELECT 0 UNION ALL SELECT x + 1 FROM n WHERE x < 364)
+    INSERT INTO repo_referrers (repo_id, date, referrer, count, uniques)
+    SELECT r.x, date('2023-01-01', '+' || d.x || ' day') || 'T00:00:00Z', 'ref-' || f.x,
+      (d.x * 7 + f.x * 3) % 50, (d.x * 5 + r.x) % 20
+    FROM n d, n r, n f WHERE r.x < 20 AND f.x < 20;
+    ";
+    sqlx::query(qs).execute(&db.db).await.unwrap();
+    db.update_deltas_full().await.unwrap();
+
+    // new date added between runs
+    let qs = "
+    INSERT INTO repo_referrers (repo_id, date, referrer, count, uniques)
+    SELECT repo_id, '2024-01-01T00:00:00Z', referrer, count + 3, uniques FROM repo_referrers
+    WHERE date = '2023-12-31T00:00:00Z';
+    ";
+    sqlx::query(qs).execute(&db.db).await.unwrap();
+
+    let deltas = || async {
+      let qs = "SELECT SUM(count_delta), SUM(uniques_delta), COUNT(*) FROM repo_referrers;";
+      sqlx::query_as::<_, (i64, i64, i64)>(qs).fetch_one(&db.db).await.unwrap()
+    };
+
+    let stime = std::time::Instant::now();
+    db.update_deltas_for(3).await.unwrap();
+    let incremental_time = stime.elapsed();
+    let incremental = deltas().await;
+
+    let stime = std::time::Instant::now();
+    db.update_deltas_full().await.unwrap();
+    let full_time = stime.elapsed();
+
+    assert_eq!(incremental, deltas().await);
+    assert!(
+      incremental_time < full_time,
+      "rows: {}, incremental: {:?}, full: {:?}",
+      incremental.2,
+      incremental_time,
+      full_time
+    );
+  }
+
+  #[tokio::test]
+  async fn test_import_snapshot() {
+    let src = test_db().await;
+    src.insert_repo(&test_repo(1, "foo/old")).await.unwrap();
+    let topics = vec!["rust".to_string()];
+    let repo = Repo { stargazers_count: 7, watched: true, topics, ..test_repo(1, "foo/bar") };
+    src.seed_repo(&repo, "2024-01-01T00:00:00Z").await;
+    let refs = vec![RepoReferrer { referrer: "google".to_string(), count: 3, uniques: 2 }];
+    src.insert_referrers(&repo, "2024-01-01T00:00:00Z", &refs).await.unwrap();
+
+    let data = src.export_data().await.unwrap();
+    let dst = test_db().await;
+    data.validate(dst.get_version().await.unwrap()).unwrap();
+    dst.import_data(&data).await.unwrap();
+
+    let totals = dst.get_repo_totals("foo/bar").await.unwrap().unwrap();
+    assert_eq!(totals.stars, 7);
+    assert_eq!(dst.export_data().await.unwrap().repos[0].topics, vec!["rust"]);
+    assert!(dst.export_data().await.unwrap().repos[0].watched);
+    assert_eq!(dst.get_renamed_repo("foo/old").await.unwrap(), Some("foo/bar".to_string()));
+    let refs = dst.export_data().await.unwrap().referrers;
+    assert_eq!((refs.len(), refs[0].count), (1, 3));
+
+    // re-import keeps greater values
+    dst.import_data(&data).await.unwrap();
+    assert_eq!(dst.export_data().await.unwrap().stats.len(), 1);
+
+    // wrong version or rows of unknown repos rejected
+    assert!(data.validate(data.version + 1).is_err());
+    let mut broken = data.clone();
+    broken.stats[0].repo_id = 42;
+    assert!(broken.validate(data.version).is_err());
+
+    // jsonl lines of unknown repo rejected as whole
+    let line = ExportStatsLine { repo: "foo/bar".to_string(), stats: data.stats[0].clone() };
+    let mut lines = vec![line.clone(), line];
+    lines[1].stats.repo_id = 42;
+    assert!(dst.import_stats(&lines).await.is_err());
+    lines.pop();
+    dst.import_stats(&lines).await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_detect_gaps() {
+    let db = test_db().await;
+    let repo = test_repo(1, "foo/bar");
+
+    // days without traffic still count as collected, days before first traffic are ignored
+    for day in ["01", "05", "06", "09"] {
+      let date = format!("2024-01-{day}T00:00:00Z");
+      for repo in [&repo, &test_repo(2, "foo/idle")] {
+        db.seed_repo(repo, &date).await;
+      }
+    }
+
+    let daily = crate::gh_client::TrafficDaily {
+      timestamp: "2024-01-05T00:00:00Z".to_string(),
+      uniques: 1,
+      count: 2,
+    };
+    let views = RepoViews { uniques: 1, count: 2, views: vec![daily] };
+    db.insert_views(&repo, &views).await.unwrap();
+
+    let gaps = db.detect_gaps(None).await.unwrap();
+    assert_eq!(gaps.len(), 1);
+    assert_eq!((gaps[0].name.as_str(), gaps[0].days), ("foo/bar", 2));
+    assert_eq!(gaps[0].gap_start, "2024-01-06T00:00:00Z");
+    assert_eq!(db.detect_gaps(Some("foo/idle")).await.unwrap().len(), 0);
+  }
+}
@@ -1,16 +1,19 @@
-use std::{time::Duration, vec};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::{collections::HashMap, sync::Mutex, time::Duration, vec};
 
+use chrono::{DateTime, Utc};
 use reqwest::{
   header::{HeaderMap, HeaderValue},
-  RequestBuilder,
+  RequestBuilder, StatusCode,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::Semaphore;
 
 use crate::types::Res;
 
 // MARK: Types
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Repo {
   pub id: u64,
   pub full_name: String,
@@ -21,6 +24,40 @@ pub struct Repo {
   pub open_issues_count: u32,
   pub fork: bool,
   pub archived: bool,
+  pub private: bool,
+  pub size: u32, // in KB
+  pub default_branch: Option<String>,
+  pub created_at: Option<String>,
+  pub pushed_at: Option<String>,
+  #[serde(default)]
+  pub topics: Vec<String>,
+  #[serde(skip)]
+  pub watched: bool, // starred repo, not owned
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+  #[default]
+  Public,
+  Private,
+  All,
+}
+
+impl Visibility {
+  pub fn is_allowed(&self, private: bool) -> bool {
+    match self {
+      Visibility::Public => !private,
+      Visibility::Private => private,
+      Visibility::All => true,
+    }
+  }
+}
+
+impl std::fmt::Display for Visibility {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", serde_variant::to_variant_name(self).unwrap())
+  }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -29,6 +66,12 @@ pub struct PullRequest {
   pub title: String,
 }
 
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct IssueCounts {
+  pub open: u32,
+  pub closed: u32,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TrafficDaily {
   pub timestamp: String,
@@ -65,6 +108,35 @@ pub struct RepoReferrer {
   pub uniques: u32,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReleaseAsset {
+  pub download_count: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Release {
+  pub tag_name: String,
+  pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RateLimit {
+  pub limit: u32,
+  pub remaining: u32,
+  pub used: u32,
+  pub reset: i64, // unix timestamp
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResources {
+  core: RateLimit,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResponse {
+  resources: RateLimitResources,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RepoStar {
   pub starred_at: String,
@@ -72,50 +144,383 @@ pub struct RepoStar {
 
 // MARK: GhClient
 
+struct CachedResponse {
+  etag: String,
+  link: String,
+  body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AppClaims {
+  iat: i64,
+  exp: i64,
+  iss: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstallationToken {
+  token: String,
+  expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstallationRepos {
+  repositories: Vec<Repo>,
+}
+
+/// Outcomes & time of GitHub API calls to one endpoint, collected per `update_metrics` run
+#[derive(Debug, Default, Clone)]
+pub struct EndpointStats {
+  pub calls: u32,
+  pub ok: u32, // 2xx & 3xx (incl. 304 from cache)
+  pub client_errors: u32,
+  pub server_errors: u32,
+  pub failed: u32, // network errors, timeouts
+  pub total_ms: u128,
+}
+
+/// Endpoint path with repo name replaced, so stats of all repos are grouped together
+fn endpoint_name(url: &reqwest::Url) -> String {
+  let segs = url.path().trim_matches('/').split('/').collect::<Vec<_>>();
+  match segs.as_slice() {
+    ["repos", _, _, rest @ ..] if !rest.is_empty() => {
+      format!("/repos/:owner/:repo/{}", rest.join("/"))
+    }
+    ["repos", _, _] => "/repos/:owner/:repo".to_string(),
+    _ => url.path().to_string(),
+  }
+}
+
+/// GitHub App installation auth: JWT signed with app key exchanged for short-living token
+struct GhApp {
+  app_id: String,
+  installation_id: String,
+  key: jsonwebtoken::EncodingKey,
+  token: tokio::sync::Mutex<Option<InstallationToken>>,
+}
+
 pub struct GhClient {
   client: reqwest::Client,
   base_url: String,
+  cache: Mutex<HashMap<String, CachedResponse>>,
+  anonymous: bool,
+  app: Option<GhApp>,
+  tokens: Vec<String>,
+  token_idx: AtomicUsize,
+  dump_dir: Option<std::path::PathBuf>,
+  stats: Mutex<HashMap<String, EndpointStats>>,
+}
+
+fn auth_header(token: &str) -> Res<HeaderValue> {
+  let mut header = HeaderValue::from_str(&format!("Bearer {}", token))?;
+  header.set_sensitive(true);
+  Ok(header)
+}
+
+// https://docs.github.com/en/rest/using-the-rest-api/rate-limits-for-the-rest-api#exceeding-the-rate-limit
+fn is_rate_limited(rep: &reqwest::Response) -> bool {
+  let status = rep.status();
+  if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+    return false;
+  }
+
+  let remaining = rep.headers().get("x-ratelimit-remaining").and_then(|x| x.to_str().ok());
+  status == StatusCode::TOO_MANY_REQUESTS || remaining == Some("0")
+}
+
+/// Number of last page from `link` header (1 if list has single page)
+fn last_page(link: &str) -> u32 {
+  let last = link.split(',').find(|x| x.contains(r#"rel="last""#)).unwrap_or_default();
+  let url = last.trim().trim_start_matches('<').split('>').next().unwrap_or_default();
+  let page = url.split(['?', '&']).find_map(|x| x.strip_prefix("page="));
+  page.and_then(|x| x.parse().ok()).unwrap_or(1)
+}
+
+/// Network errors, 5xx and broken body are worth retrying, 4xx (no access, not found) are not
+fn is_transient(err: &anyhow::Error) -> bool {
+  match err.downcast_ref::<reqwest::Error>() {
+    Some(e) => e.status().is_none_or(|x| x.is_server_error()),
+    None => err.is::<serde_json::Error>(),
+  }
 }
 
 impl GhClient {
-  pub fn new(token: String) -> Res<GhClient> {
-    let user_agent = format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+  /// Client authenticated as GitHub App installation, `private_key` is PEM content or path to it
+  pub fn new_app(app_id: String, installation_id: String, private_key: String) -> Res<GhClient> {
+    let pem = match private_key.trim_start().starts_with("-----BEGIN") {
+      true => private_key.replace("\\n", "\n"), // allow single line key in env
+      false => std::fs::read_to_string(&private_key)?,
+    };
+
+    let key = jsonwebtoken::EncodingKey::from_rsa_pem(pem.as_bytes())?;
+    let token = tokio::sync::Mutex::new(None);
+
+    let mut client = Self::new(String::new())?;
+    client.anonymous = false;
+    client.app = Some(GhApp { app_id, installation_id, key, token });
+    Ok(client)
+  }
 
-    let mut auth_header = HeaderValue::from_str(&format!("Bearer {}", token))?;
-    auth_header.set_sensitive(true);
+  /// `token` can be comma separated list of tokens, next one used when current is rate limited
+  pub fn new(token: String) -> Res<GhClient> {
+    // some corporate proxies require specific UA
+    let user_agent = std::env::var("GHS_USER_AGENT").unwrap_or_default().trim().to_string();
+    let user_agent = match user_agent.is_empty() {
+      true => format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+      false => user_agent,
+    };
+
+    let user_agent_header = match HeaderValue::from_str(&user_agent) {
+      Ok(x) => x,
+      Err(_) => anyhow::bail!("invalid GHS_USER_AGENT value: {}", user_agent),
+    };
+    tracing::info!("github user agent: {}", user_agent);
 
     let mut headers = HeaderMap::new();
     headers.insert("Accept", HeaderValue::from_static("application/vnd.github+json"));
     headers.insert("X-GitHub-Api-Version", HeaderValue::from_static("2022-11-28"));
-    headers.insert("Authorization", auth_header);
-    headers.insert("User-Agent", HeaderValue::from_str(&user_agent)?);
+    headers.insert("User-Agent", user_agent_header);
+
+    // empty token means anonymous mode (public data only, 60 req/h)
+    let tokens = token.split(',').map(|x| x.trim()).filter(|x| !x.is_empty());
+    let tokens = tokens.map(|x| x.to_string()).collect::<Vec<_>>();
+    let anonymous = tokens.is_empty();
+    if tokens.len() > 1 {
+      tracing::info!("using {} github tokens with rotation", tokens.len());
+    }
+
+    let timeout = std::env::var("GHS_HTTP_TIMEOUT").unwrap_or_default();
+    let timeout = timeout.parse::<u64>().ok().filter(|x| *x > 0).unwrap_or(30);
+    tracing::info!("http timeout: {}s", timeout);
 
     let client = reqwest::Client::builder()
       .default_headers(headers)
-      .read_timeout(Duration::from_secs(30))
+      .read_timeout(Duration::from_secs(timeout))
+      .connect_timeout(Duration::from_secs(timeout))
       .build()?;
 
     let base_url = "https://api.github.com".to_string();
-    Ok(GhClient { client, base_url })
+    let cache = Mutex::new(HashMap::new());
+    // raw responses can be saved to check if wrong numbers come from github or from ghstats
+    let dump_dir = std::env::var("GHS_DEBUG_DUMP_DIR").ok().filter(|x| !x.is_empty());
+    let dump_dir = dump_dir.map(std::path::PathBuf::from);
+    if let Some(dir) = &dump_dir {
+      tracing::warn!("debug dump of github responses enabled: {}", dir.display());
+      std::fs::create_dir_all(dir)?;
+    }
+
+    Ok(GhClient {
+      client,
+      base_url,
+      cache,
+      anonymous,
+      app: None,
+      tokens,
+      token_idx: AtomicUsize::new(0),
+      dump_dir,
+      stats: Mutex::new(HashMap::new()),
+    })
   }
 
-  async fn with_pagination<T: DeserializeOwned>(&self, req: RequestBuilder) -> Res<Vec<T>> {
-    let mut items: Vec<T> = vec![];
-    let per_page = 100;
-    let mut page = 1;
+  pub fn is_anonymous(&self) -> bool {
+    self.anonymous
+  }
+
+  // https://docs.github.com/en/apps/creating-github-apps/authenticating-with-a-github-app/generating-an-installation-access-token-for-a-github-app
+  async fn installation_token(&self, app: &GhApp) -> Res<String> {
+    let mut token = app.token.lock().await;
+
+    // token lives 1 hour, refreshed a bit before expiry
+    let now = Utc::now();
+    if let Some(x) = token.as_ref() {
+      if x.expires_at - chrono::Duration::minutes(5) > now {
+        return Ok(x.token.clone());
+      }
+    }
 
+    // iat in the past to allow clock drift, exp no more than 10 min
+    let iat = (now - chrono::Duration::seconds(60)).timestamp();
+    let exp = (now + chrono::Duration::minutes(9)).timestamp();
+    let claims = AppClaims { iat, exp, iss: app.app_id.clone() };
+    let header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+    let jwt = jsonwebtoken::encode(&header, &claims, &app.key)?;
+
+    let url = format!("{}/app/installations/{}/access_tokens", self.base_url, app.installation_id);
+    let rep = self.client.post(url).bearer_auth(jwt).send().await?.error_for_status()?;
+    let dat = rep.json::<InstallationToken>().await?;
+    tracing::info!("github app installation token refreshed, expires at {}", dat.expires_at);
+
+    let rs = dat.token.clone();
+    *token = Some(dat);
+    Ok(rs)
+  }
+
+  /// Save raw response body to `GHS_DEBUG_DUMP_DIR` as `<timestamp>_<url path>.json`
+  async fn dump_response(&self, url: &str, body: &str) {
+    let dir = match &self.dump_dir {
+      Some(x) => x,
+      None => return,
+    };
+
+    let name = url.trim_start_matches(&self.base_url).trim_start_matches('/');
+    let name = name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '.', "_");
+    let name = format!("{}_{}.json", Utc::now().format("%Y%m%dT%H%M%S%.3f"), name);
+
+    if let Err(e) = tokio::fs::write(dir.join(&name), body).await {
+      tracing::warn!("failed to dump response {}: {:?}", name, e);
+    }
+  }
+
+  /// Take collected calls stats by endpoint (and reset them)
+  pub fn take_stats(&self) -> HashMap<String, EndpointStats> {
+    std::mem::take(&mut *self.stats.lock().unwrap())
+  }
+
+  /// Send request, with installation token added when authenticated as GitHub App
+  /// (or current token from `GITHUB_TOKEN` list, rotated to next one when rate limited)
+  async fn send(&self, req: RequestBuilder) -> Res<reqwest::Response> {
+    let req = req.build()?;
+    let endpoint = endpoint_name(req.url());
+
+    let stime = std::time::Instant::now();
+    let rep = self.execute(req).await;
+    let elapsed = stime.elapsed().as_millis();
+
+    let status = rep.as_ref().map(|x| x.status().as_u16()).unwrap_or(0);
+    tracing::debug!(endpoint = %endpoint, status, elapsed_ms = elapsed as u64, "github request");
+
+    let mut stats = self.stats.lock().unwrap();
+    let item = stats.entry(endpoint).or_default();
+    item.calls += 1;
+    item.total_ms += elapsed;
+    match status {
+      0 => item.failed += 1,
+      400..=499 => item.client_errors += 1,
+      500..=599 => item.server_errors += 1,
+      _ => item.ok += 1,
+    }
+
+    rep
+  }
+
+  async fn execute(&self, mut req: reqwest::Request) -> Res<reqwest::Response> {
+    if let Some(app) = &self.app {
+      let token = self.installation_token(app).await?;
+      req.headers_mut().insert("Authorization", auth_header(&token)?);
+      return Ok(self.client.execute(req).await?);
+    }
+
+    if self.tokens.is_empty() {
+      return Ok(self.client.execute(req).await?);
+    }
+
+    // each token tried once per request
+    let mut attempts = self.tokens.len();
     loop {
-      let req = req.try_clone().unwrap();
-      let req = req.query(&[("per_page", &per_page.to_string())]);
-      let req = req.query(&[("page", &page.to_string())]);
-      let rep = req.send().await?.error_for_status()?;
+      let idx = self.token_idx.load(Ordering::Relaxed) % self.tokens.len();
+      let mut cur = match req.try_clone() {
+        Some(x) => x,
+        None => anyhow::bail!("failed to clone request"),
+      };
+      cur.headers_mut().insert("Authorization", auth_header(&self.tokens[idx])?);
+
+      let rep = self.client.execute(cur).await?;
+      attempts -= 1;
+      if attempts == 0 || !is_rate_limited(&rep) {
+        return Ok(rep);
+      }
 
-      let cur = match rep.headers().get("link") {
-        Some(l) => l.to_str().unwrap().to_string(),
-        None => "".to_string(),
+      // other request may already switched token, so rotate only from current one
+      let next = (idx + 1) % self.tokens.len();
+      let _ = self.token_idx.compare_exchange(idx, next, Ordering::Relaxed, Ordering::Relaxed);
+      tracing::warn!("github token #{} rate limited, switching to #{}", idx + 1, next + 1);
+    }
+  }
+
+  /// Send request with `If-None-Match` if ETag for this url is known. `304` responses
+  /// do not count against rate limit, previously stored body is returned for them.
+  /// Returns (link header, body)
+  async fn send_cached(&self, req: RequestBuilder, use_cache: bool) -> Res<(String, String)> {
+    let (client, req) = req.build_split();
+    let mut req = req?;
+    let url = req.url().to_string();
+
+    if use_cache {
+      let etag = self.cache.lock().unwrap().get(&url).map(|x| x.etag.clone());
+      if let Some(etag) = etag {
+        req.headers_mut().insert("If-None-Match", HeaderValue::from_str(&etag)?);
+      }
+    }
+
+    let rep = self.send(RequestBuilder::from_parts(client, req)).await?;
+    if use_cache && rep.status() == StatusCode::NOT_MODIFIED {
+      let cached = self.cache.lock().unwrap().get(&url).map(|x| (x.link.clone(), x.body.clone()));
+      if let Some(cached) = cached {
+        return Ok(cached);
+      }
+    }
+
+    let rep = rep.error_for_status()?;
+    let header = |name: &str| match rep.headers().get(name) {
+      Some(x) => x.to_str().unwrap_or_default().to_string(),
+      None => "".to_string(),
+    };
+
+    let etag = header("etag");
+    let link = header("link");
+    let body = rep.text().await?;
+    self.dump_response(&url, &body).await;
+
+    if use_cache && !etag.is_empty() {
+      let item = CachedResponse { etag, link: link.clone(), body: body.clone() };
+      self.cache.lock().unwrap().insert(url, item);
+    }
+
+    Ok((link, body))
+  }
+
+  /// Single page of list with its `link` header. Failed page retried alone, so transient error
+  /// on late page does not lose previous ones (partial list is never returned: repos missing
+  /// from it would be marked as hidden)
+  async fn get_page<T: DeserializeOwned>(
+    &self,
+    req: &RequestBuilder,
+    page: u32,
+    use_cache: bool,
+  ) -> Res<(String, Vec<T>)> {
+    let mut attempt = 0;
+    loop {
+      let req = req.try_clone().unwrap();
+      let req = req.query(&[("per_page", "100"), ("page", &page.to_string())]);
+      let res = match self.send_cached(req, use_cache).await {
+        Ok((cur, body)) => {
+          serde_json::from_str::<Vec<T>>(&body).map(|x| (cur, x)).map_err(Into::into)
+        }
+        Err(e) => Err(e),
       };
 
-      let dat = rep.json::<Vec<T>>().await?;
+      match res {
+        Ok(x) => return Ok(x),
+        Err(e) if attempt < 2 && is_transient(&e) => {
+          attempt += 1;
+          tracing::warn!("failed to load page {}, retry #{}: {:?}", page, attempt, e);
+          tokio::time::sleep(Duration::from_secs(attempt * 2)).await;
+        }
+        Err(e) => return Err(e.context(format!("failed to load page {}", page))),
+      }
+    }
+  }
+
+  async fn with_pagination<T: DeserializeOwned>(
+    &self,
+    req: RequestBuilder,
+    use_cache: bool,
+  ) -> Res<Vec<T>> {
+    let mut items: Vec<T> = vec![];
+    let mut page = 1;
+
+    loop {
+      let (cur, dat) = self.get_page(&req, page, use_cache).await?;
       items.extend(dat);
 
       match cur.contains(r#"rel="next""#) {
@@ -128,64 +533,246 @@ impl GhClient {
   }
 
   // https://docs.github.com/en/rest/repos/repos?apiVersion=2022-11-28#list-repositories-for-the-authenticated-user
-  pub async fn get_repos(&self, include_private: bool) -> Res<Vec<Repo>> {
-    let visibility = if include_private { "all" } else { "public" };
-    let url = format!("{}/user/repos?visibility={}", self.base_url, visibility);
+  /// `affiliation` is comma separated list of `owner`, `collaborator`, `organization_member`
+  /// (empty for GitHub default, which is all of them)
+  /// If `org` given, repos of this organization listed instead (`affiliation` not used)
+  pub async fn get_repos(
+    &self,
+    visibility: &Visibility,
+    affiliation: &str,
+    org: Option<&str>,
+  ) -> Res<Vec<Repo>> {
+    if let Some(org) = org {
+      return self.get_org_repos(org, visibility).await;
+    }
+
+    if self.app.is_some() {
+      return self.get_installation_repos(visibility).await;
+    }
+
+    let mut url = format!("{}/user/repos?visibility={}", self.base_url, visibility);
+    if !affiliation.is_empty() {
+      url.push_str(&format!("&affiliation={}", affiliation));
+    }
+
     let req = self.client.get(url);
-    let dat: Vec<Repo> = self.with_pagination(req).await?;
+    let dat: Vec<Repo> = self.with_pagination(req, true).await?;
     Ok(dat)
   }
 
+  // https://docs.github.com/en/rest/repos/repos?apiVersion=2022-11-28#list-organization-repositories
+  async fn get_org_repos(&self, org: &str, visibility: &Visibility) -> Res<Vec<Repo>> {
+    // `type` accepts same `all`, `public`, `private` values
+    let url = format!("{}/orgs/{}/repos?type={}", self.base_url, org, visibility);
+    let req = self.client.get(url);
+    let dat: Vec<Repo> = self.with_pagination(req, true).await?;
+
+    // private repos never returned without token or org access, filter in case of `type` ignored
+    let dat = dat.into_iter().filter(|x| visibility.is_allowed(x.private)).collect();
+    Ok(dat)
+  }
+
+  // installation token has no user, so repos listed from installation
+  // https://docs.github.com/en/rest/apps/installations?apiVersion=2022-11-28#list-repositories-accessible-to-the-app-installation
+  async fn get_installation_repos(&self, visibility: &Visibility) -> Res<Vec<Repo>> {
+    let mut repos = vec![];
+    let mut page = 1;
+
+    loop {
+      let url = format!("{}/installation/repositories", self.base_url);
+      let req = self.client.get(url).query(&[("per_page", "100"), ("page", &page.to_string())]);
+      let (link, body) = self.send_cached(req, true).await?;
+      repos.extend(serde_json::from_str::<InstallationRepos>(&body)?.repositories);
+
+      match link.contains(r#"rel="next""#) {
+        true => page += 1,
+        false => break,
+      }
+    }
+
+    let repos = repos.into_iter().filter(|x| visibility.is_allowed(x.private)).collect();
+    Ok(repos)
+  }
+
+  // https://docs.github.com/en/rest/activity/starring?apiVersion=2022-11-28#list-repositories-starred-by-the-authenticated-user
+  pub async fn get_starred(&self) -> Res<Vec<Repo>> {
+    if self.app.is_some() {
+      anyhow::bail!("starred repos not available with GitHub App auth");
+    }
+
+    let url = format!("{}/user/starred", self.base_url);
+    let req = self.client.get(url);
+    let dat: Vec<Repo> = self.with_pagination(req, true).await?;
+    Ok(dat)
+  }
+
+  // https://docs.github.com/en/rest/repos/repos?apiVersion=2022-11-28#get-a-repository
+  pub async fn get_repos_by_name(&self, names: &[String]) -> Res<Vec<Repo>> {
+    let mut repos = vec![];
+    for name in names.iter().filter(|x| !x.ends_with("/*")) {
+      let url = format!("{}/repos/{}", self.base_url, name);
+      let (_, body) = match self.send_cached(self.client.get(url), true).await {
+        Ok(x) => x,
+        Err(e) => {
+          tracing::warn!("failed to get repo {}: {:?}", name, e);
+          continue;
+        }
+      };
+
+      repos.push(serde_json::from_str::<Repo>(&body)?);
+    }
+
+    Ok(repos)
+  }
+
   pub async fn get_open_pull_requests(&self, repo: &str) -> Res<Vec<PullRequest>> {
     let url = format!("{}/repos/{}/pulls?state=open", self.base_url, repo);
     let req = self.client.get(url);
-    let dat: Vec<PullRequest> = self.with_pagination(req).await?;
+    let dat: Vec<PullRequest> = self.with_pagination(req, true).await?;
     Ok(dat)
   }
 
+  // `open_issues_count` of repo includes open PRs, so real issues count requested separately
+  // graphql used here, because search api has much lower rate limit (30 req/min)
+  // https://docs.github.com/en/graphql/reference/objects#repository
+  pub async fn get_issue_counts(&self, repo: &str) -> Res<IssueCounts> {
+    let (owner, name) = repo.split_once('/').unwrap_or((repo, ""));
+    let query = "query($owner: String!, $name: String!) {
+      repository(owner: $owner, name: $name) {
+        open: issues(states: OPEN) { totalCount }
+        closed: issues(states: CLOSED) { totalCount }
+      }
+    }";
+
+    let body = serde_json::json!({ "query": query, "variables": { "owner": owner, "name": name } });
+    let url = format!("{}/graphql", self.base_url);
+    let rep = self.send(self.client.post(url).json(&body)).await?.error_for_status()?;
+    let dat = rep.json::<serde_json::Value>().await?;
+
+    // graphql returns 200 with `errors` list (e.g. no access), data can be partial then
+    if let Some(errors) = dat["errors"].as_array().filter(|x| !x.is_empty()) {
+      let msg = errors[0]["message"].as_str().unwrap_or("unknown error");
+      anyhow::bail!("failed to get issues count for {}: {}", repo, msg);
+    }
+
+    let dat = &dat["data"]["repository"];
+    if dat.is_null() {
+      anyhow::bail!("failed to get issues count for {}", repo);
+    }
+
+    // missing count is error, not zero – otherwise wrong value saved to history
+    let count = |key: &str| match dat[key]["totalCount"].as_u64() {
+      Some(x) => Ok(x as u32),
+      None => Err(anyhow::anyhow!("unexpected issues count response for {}: {}", repo, dat)),
+    };
+
+    Ok(IssueCounts { open: count("open")?, closed: count("closed")? })
+  }
+
   // https://docs.github.com/en/rest/metrics/traffic?apiVersion=2022-11-28
   pub async fn traffic_clones(&self, repo: &str) -> Res<RepoClones> {
     let url = format!("{}/repos/{}/traffic/clones", self.base_url, repo);
-    let rep = self.client.get(url).send().await?.error_for_status()?;
-    let dat = rep.json::<RepoClones>().await?;
+    let (_, body) = self.send_cached(self.client.get(url), true).await?;
+    let dat = serde_json::from_str::<RepoClones>(&body)?;
     Ok(dat)
   }
 
   pub async fn traffic_views(&self, repo: &str) -> Res<RepoViews> {
     let url = format!("{}/repos/{}/traffic/views", self.base_url, repo);
-    let rep = self.client.get(url).send().await?.error_for_status()?;
-    let dat = rep.json::<RepoViews>().await?;
+    let (_, body) = self.send_cached(self.client.get(url), true).await?;
+    let dat = serde_json::from_str::<RepoViews>(&body)?;
     Ok(dat)
   }
 
   pub async fn traffic_paths(&self, repo: &str) -> Res<Vec<RepoPopularPath>> {
     let url = format!("{}/repos/{}/traffic/popular/paths", self.base_url, repo);
-    let rep = self.client.get(url).send().await?.error_for_status()?;
-    let dat = rep.json::<Vec<RepoPopularPath>>().await?;
+    let (_, body) = self.send_cached(self.client.get(url), true).await?;
+    let dat = serde_json::from_str::<Vec<RepoPopularPath>>(&body)?;
     Ok(dat)
   }
 
   pub async fn traffic_refs(&self, repo: &str) -> Res<Vec<RepoReferrer>> {
     let url = format!("{}/repos/{}/traffic/popular/referrers", self.base_url, repo);
-    let rep = self.client.get(url).send().await?.error_for_status()?;
-    let dat = rep.json::<Vec<RepoReferrer>>().await?;
+    let (_, body) = self.send_cached(self.client.get(url), true).await?;
+    let dat = serde_json::from_str::<Vec<RepoReferrer>>(&body)?;
     Ok(dat)
   }
 
-  pub async fn get_latest_release_ver(&self, repo: &str) -> Res<String> {
+  // https://docs.github.com/en/rest/rate-limit/rate-limit?apiVersion=2022-11-28
+  // >> Accessing this endpoint does not count against your REST API rate limit.
+  pub async fn get_rate_limit(&self) -> Res<RateLimit> {
+    let url = format!("{}/rate_limit", self.base_url);
+    let rep = self.send(self.client.get(url)).await?.error_for_status()?;
+    let dat = rep.json::<RateLimitResponse>().await?;
+    Ok(dat.resources.core)
+  }
+
+  /// Latest release version without `v` prefix, `None` if repo has no releases
+  pub async fn get_latest_release_ver(&self, repo: &str) -> Res<Option<String>> {
     let url = format!("{}/repos/{}/releases/latest", self.base_url, repo);
-    let rep = self.client.get(url).send().await?.error_for_status()?;
-    let dat = rep.json::<serde_json::Value>().await?;
-    let ver = dat["tag_name"].as_str().unwrap().to_string();
-    let ver = ver.trim_start_matches("v").to_string();
-    Ok(ver)
+    let rep = self.send(self.client.get(url)).await?;
+    if rep.status() == StatusCode::NOT_FOUND {
+      return Ok(None);
+    }
+
+    let dat = rep.error_for_status()?.json::<serde_json::Value>().await?;
+    let ver = match dat["tag_name"].as_str() {
+      Some(x) => x.trim_start_matches("v").to_string(),
+      None => {
+        tracing::warn!("unexpected latest release response for {}: {}", repo, dat);
+        return Ok(None);
+      }
+    };
+
+    Ok(Some(ver))
+  }
+
+  // https://docs.github.com/en/rest/releases/releases?apiVersion=2022-11-28#list-releases
+  /// Total downloads of assets across all releases (0 if repo has no releases)
+  pub async fn get_release_download_counts(&self, repo: &str) -> Res<u64> {
+    let url = format!("{}/repos/{}/releases", self.base_url, repo);
+    let req = self.client.get(url);
+    let dat: Vec<Release> = self.with_pagination(req, true).await?;
+    let total = dat.iter().flat_map(|x| &x.assets).map(|x| x.download_count).sum();
+    Ok(total)
   }
 
-  pub async fn get_stars(&self, repo: &str) -> Res<Vec<RepoStar>> {
+  /// First page gives pages count (`rel="last"` link), rest loaded in parallel – each request
+  /// holds one of `permits`, so concurrency is shared by all repos synced at the same time.
+  /// Loaded pages counted in `pages`, `None` if `budget` already spent before repo started
+  pub async fn get_stars(
+    &self,
+    repo: &str,
+    permits: &Semaphore,
+    pages: &AtomicU32,
+    budget: u32,
+  ) -> Res<Option<Vec<RepoStar>>> {
     let url = format!("{}/repos/{}/stargazers", self.base_url, repo);
     let req = self.client.get(url).header("Accept", "application/vnd.github.v3.star+json");
 
-    let dat: Vec<RepoStar> = self.with_pagination(req).await?;
-    return Ok(dat);
+    // stars synced once per repo, so no reason to keep them in etag cache
+    let load = |page: u32| {
+      let req = &req;
+      async move {
+        let _permit = permits.acquire().await?;
+        self.get_page::<RepoStar>(req, page, false).await
+      }
+    };
+
+    // repo started only while budget left, shared by all repos in progress
+    let started =
+      pages.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| (x < budget).then_some(x + 1));
+    if started.is_err() {
+      return Ok(None);
+    }
+
+    // started repo is always finished (can go over budget), so loaded pages are never lost
+    let (link, mut items) = load(1).await?;
+    let last = last_page(&link);
+    pages.fetch_add(last.saturating_sub(1), Ordering::Relaxed);
+
+    let rest = futures::future::try_join_all((2..=last).map(load)).await?;
+    items.extend(rest.into_iter().flat_map(|x| x.1));
+    Ok(Some(items))
   }
 }
@@ -1,24 +1,235 @@
-use std::{collections::HashMap, sync::Arc};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
 
 use axum::extract::Request;
+use futures::{stream, StreamExt};
+use regex::{Regex, RegexBuilder};
+use tokio::sync::Semaphore;
 
 use crate::{
-  db_client::DbClient,
-  gh_client::{GhClient, Repo},
-  state::AppState,
+  db_client::{DbClient, RepoFilter, RepoItem, RepoSort},
+  gh_client::{EndpointStats, GhClient, Repo},
+  state::{AppState, RunStatus},
   types::Res,
 };
 
+/// Shorten text to `max_len` chars with "..." in the middle (counted in chars, not bytes)
 pub fn truncate_middle(text: &str, max_len: usize) -> String {
-  if text.len() <= max_len {
+  let len = text.chars().count();
+  if len <= max_len {
     return text.to_string();
   }
 
-  let part_len = (max_len - 3) / 2;
-  let start = &text[..part_len];
-  let end = &text[text.len() - part_len..];
+  // byte offsets of char boundaries, so multibyte chars are never split
+  let part_len = max_len.saturating_sub(3) / 2;
+  let start = text.char_indices().nth(part_len).map_or(text.len(), |x| x.0);
+  let end = text.char_indices().nth(len - part_len).map_or(text.len(), |x| x.0);
 
-  format!("{}...{}", start, end)
+  format!("{}...{}", &text[..start], &text[end..])
+}
+
+/// Escape text for XML content & attributes
+pub fn xml_escape(text: &str) -> String {
+  let mut rs = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => rs.push_str("&amp;"),
+      '<' => rs.push_str("&lt;"),
+      '>' => rs.push_str("&gt;"),
+      '"' => rs.push_str("&quot;"),
+      '\'' => rs.push_str("&apos;"),
+      _ => rs.push(c),
+    }
+  }
+  rs
+}
+
+/// Percent-encode query string value (RFC 3986 unreserved chars kept as is)
+pub fn url_encode(text: &str) -> String {
+  let mut rs = String::with_capacity(text.len());
+  for b in text.bytes() {
+    match b {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => rs.push(b as char),
+      _ => rs.push_str(&format!("%{:02X}", b)),
+    }
+  }
+  rs
+}
+
+/// Render shields.io-like flat badge
+pub fn render_badge(label: &str, value: &str, color: &str) -> String {
+  // approximate text width for 11px Verdana
+  let lw = label.chars().count() * 7 + 10;
+  let vw = value.chars().count() * 7 + 10;
+  let (w, lx, vx) = (lw + vw, lw / 2, lw + vw / 2);
+  let (label, value) = (xml_escape(label), xml_escape(value));
+
+  format!(
+    r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="20" role="img" aria-label="{label}: {value}">
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{w}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{lw}" height="20" fill="#555"/><rect x="{lw}" width="{vw}" height="20" fill="{color}"/><rect width="{w}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+<text x="{lx}" y="14">{label}</text><text x="{vx}" y="14">{value}</text>
+</g>
+</svg>"##
+  )
+}
+
+/// Format size given in KB to human readable string
+pub fn format_size(kb: i64) -> String {
+  let units = ["KB", "MB", "GB", "TB"];
+  let mut size = kb.max(0) as f64;
+  let mut unit = 0;
+  while size >= 1024.0 && unit < units.len() - 1 {
+    size /= 1024.0;
+    unit += 1;
+  }
+
+  match unit {
+    0 => format!("{} {}", size, units[unit]),
+    _ => format!("{:.1} {}", size, units[unit]),
+  }
+}
+
+/// Format number in compact notation, e.g. 1234 -> "1.2K", 1234567 -> "1.2M"
+pub fn format_compact(value: i64) -> String {
+  let units = ["", "K", "M", "B", "T"];
+  let mut num = value.unsigned_abs() as f64;
+  let mut unit = 0;
+  // compare with rounded value, so 999_999 is "1M" not "1000K"
+  while num >= 999.95 && unit < units.len() - 1 {
+    num /= 1000.0;
+    unit += 1;
+  }
+
+  let sign = if value < 0 { "-" } else { "" };
+  let num = match unit {
+    0 => format!("{}", num),
+    _ => format!("{:.1}", num).trim_end_matches(".0").to_string(),
+  };
+
+  format!("{}{}{}", sign, num, units[unit])
+}
+
+/// Parse periods list like "7,30,365" into `(days, label)` options, "All time" is always last
+pub fn parse_periods(val: &str) -> Vec<(i32, String)> {
+  let mut days = val
+    .split(',')
+    .filter_map(|x| x.trim().parse::<i32>().ok())
+    .filter(|x| *x > 0)
+    .collect::<Vec<_>>();
+
+  if days.is_empty() {
+    days = vec![7, 14, 30, 90];
+  }
+
+  days.sort();
+  days.dedup();
+
+  let mut periods = days.into_iter().map(|x| (x, format!("Last {} days", x))).collect::<Vec<_>>();
+  periods.push((-1, "All time".to_string()));
+  periods
+}
+
+/// Home table columns in default order: `RepoSort` names plus not sortable `trend`
+pub const DEFAULT_COLUMNS: &str =
+  "name,issues_open,prs,forks,watchers,clones_count,stars,stars_new,trend,views_count,pushed_at";
+
+/// Parse columns list like "name,stars,views" into canonical column keys, order kept
+pub fn parse_columns(val: &str) -> Res<Vec<String>> {
+  let val = match val.trim() {
+    "" => DEFAULT_COLUMNS,
+    x => x,
+  };
+
+  let mut cols: Vec<String> = vec![];
+  for name in val.split(',').map(|x| x.trim().to_lowercase()).filter(|x| !x.is_empty()) {
+    let col = match name.as_str() {
+      "trend" => name,
+      _ => match serde_json::from_value::<RepoSort>(serde_json::Value::String(name.clone())) {
+        Ok(x) => x.to_string(),
+        Err(_) => anyhow::bail!("invalid GHS_COLUMNS value: {}", name),
+      },
+    };
+
+    if !cols.contains(&col) {
+      cols.push(col);
+    }
+  }
+
+  Ok(cols)
+}
+
+/// Normalize timestamp to UTC day key `YYYY-MM-DDT00:00:00Z` (db stores dates as UTC midnight)
+pub fn day_key(ts: &str) -> String {
+  // timestamps with offset converted to UTC first, so same moment always gets same day
+  let date = match chrono::DateTime::parse_from_rfc3339(ts.trim()) {
+    Ok(x) => x.with_timezone(&chrono::Utc).format("%Y-%m-%d").to_string(),
+    Err(_) => ts.trim().split("T").next().unwrap_or_default().to_string(),
+  };
+
+  format!("{date}T00:00:00Z")
+}
+
+/// Move pinned items to front (in pin order), rest keep their order; returns number of pinned
+pub fn pin_first<T>(
+  items: Vec<T>,
+  pinned: &[String],
+  name: impl Fn(&T) -> &str,
+) -> (Vec<T>, usize) {
+  let mut rest = items;
+  let mut front = vec![];
+  for pin in pinned {
+    if let Some(idx) = rest.iter().position(|x| name(x).eq_ignore_ascii_case(pin)) {
+      front.push(rest.remove(idx));
+    }
+  }
+
+  let count = front.len();
+  front.extend(rest);
+  (front, count)
+}
+
+/// Format elapsed seconds as "5 min ago"
+pub fn format_ago(secs: i64) -> String {
+  match secs.max(0) {
+    x if x < 60 => "just now".to_string(),
+    x if x < 3600 => format!("{} min ago", x / 60),
+    x if x < 86400 => format!("{} h ago", x / 3600),
+    x => format!("{} d ago", x / 86400),
+  }
+}
+
+/// Build listen address from `HOST` / `PORT` values (IPv6 can be passed as `::` or `[::]`)
+pub fn parse_listen_addr(host: &str, port: &str) -> Res<String> {
+  let port: u16 = match port.trim().parse() {
+    Ok(x) => x,
+    Err(_) => anyhow::bail!("invalid PORT value: {}, expected number 0-65535", port),
+  };
+
+  let host = host.trim();
+  let bare = host.strip_prefix('[').and_then(|x| x.strip_suffix(']')).unwrap_or(host);
+  if let Ok(ip) = bare.parse::<std::net::IpAddr>() {
+    return Ok(std::net::SocketAddr::new(ip, port).to_string());
+  }
+
+  let is_hostname = !bare.is_empty()
+    && bare.len() <= 253
+    && bare.split('.').all(|x| {
+      !x.is_empty()
+        && x.len() <= 63
+        && !x.starts_with('-')
+        && !x.ends_with('-')
+        && x.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+
+  if !is_hostname {
+    anyhow::bail!("invalid HOST value: {}, expected IP address or hostname", host);
+  }
+
+  Ok(format!("{}:{}", bare, port))
 }
 
 pub fn get_header<'a>(req: &'a Request, name: &'a str) -> Option<&'a str> {
@@ -37,65 +248,329 @@ async fn check_hidden_repos(db: &DbClient, repos: &Vec<Repo>) -> Res {
   Ok(())
 }
 
+#[derive(Clone, Copy, Default)]
+struct RunCounts {
+  repos: usize,
+  errors: usize,
+  new_stars: i64,
+}
+
+/// Sync all repos, outcome saved to `last_run` (exposed in `/api/status`)
 pub async fn update_metrics(state: Arc<AppState>) -> Res {
+  let (started_at, stime) = (chrono::Utc::now(), std::time::Instant::now());
+  let res = run_update(&state).await;
+  let counts = res.as_ref().map(|x| *x).unwrap_or_default();
+
+  let status = RunStatus {
+    started_at,
+    finished_at: chrono::Utc::now(),
+    duration_ms: stime.elapsed().as_millis() as u64,
+    repos: counts.repos,
+    errors: counts.errors,
+    new_stars: counts.new_stars,
+    error: res.as_ref().err().map(|e| format!("{:?}", e)),
+  };
+
+  // single event with fields, so log processors get run metrics without parsing messages
+  tracing::info!(
+    event = "sync_summary",
+    ok = status.error.is_none(),
+    repos = status.repos,
+    duration_ms = status.duration_ms,
+    errors = status.errors,
+    new_stars = status.new_stars,
+    "sync summary"
+  );
+
+  *state.last_run.lock().unwrap() = Some(status);
+
+  res.map(|_| ())
+}
+
+async fn run_update(state: &Arc<AppState>) -> Res<RunCounts> {
   let stime = std::time::Instant::now();
 
-  let date = chrono::Utc::now().to_utc().to_rfc3339();
-  let date = date.split("T").next().unwrap().to_owned() + "T00:00:00Z";
+  let date = day_key(&chrono::Utc::now().to_rfc3339());
+  let _ = state.gh.take_stats(); // count only calls of this run
+
+  let org = state.org.as_deref();
+  let mut repos = match state.gh.is_anonymous() && org.is_none() {
+    // user repos not available without token, so only explicitly listed repos loaded
+    true => state.gh.get_repos_by_name(&state.filter.include_repos).await?,
+    false => state.gh.get_repos(&state.visibility, &state.affiliation, org).await?,
+  };
+
+  // starred repos are tracked as watched (own repos starred by user stay as is)
+  let mut starred_failed = false;
+  if state.track_starred && !state.gh.is_anonymous() {
+    let ids = repos.iter().map(|x| x.id).collect::<HashSet<_>>();
+    match state.gh.get_starred().await {
+      Ok(starred) => {
+        let starred = starred
+          .into_iter()
+          .filter(|x| !ids.contains(&x.id) && state.visibility.is_allowed(x.private))
+          .map(|x| Repo { watched: true, ..x });
+        repos.extend(starred);
+      }
+      Err(e) => {
+        tracing::warn!("failed to get starred repos: {:?}", e);
+        starred_failed = true;
+      }
+    }
+  }
+
+  // watched repos missing from incomplete list should not be marked as hidden
+  if !starred_failed {
+    let _ = check_hidden_repos(&state.db, &repos).await?;
+  }
 
-  let repos = state.gh.get_repos(state.include_private).await?;
-  let _ = check_hidden_repos(&state.db, &repos).await?;
+  // report filter typos once, on first sync after start
+  if state.last_update.lock().unwrap().is_none() {
+    let names = repos.iter().map(|x| x.full_name.as_str()).collect::<Vec<_>>();
+    for rule in state.filter.unmatched_rules(&names) {
+      tracing::warn!("GHS_FILTER rule {} does not match any repo", rule);
+    }
+  }
 
   let repos = repos //
     .iter()
-    .filter(|r| state.filter.is_included(&r.full_name, r.fork, r.archived))
+    .filter(|r| state.filter.is_included(&r.full_name, r.fork, r.archived, r.stargazers_count))
     .collect::<Vec<_>>();
 
-  for repo in &repos {
-    match update_repo_metrics(&state.db, &state.gh, &repo, &date).await {
-      Err(e) => {
-        tracing::warn!("failed to update metrics for {}: {:?}", repo.full_name, e);
+  // stars before this run, new repos not counted (all their stars would look new)
+  let prev_stars = state.db.get_repos(&RepoFilter::default()).await?;
+  let prev_stars =
+    prev_stars.into_iter().map(|x| (x.id, x.stars as i64)).collect::<HashMap<_, _>>();
+
+  // db writes go through shared pool (sqlite waits on lock), github requests run concurrently
+  // futures collected upfront: lazy map closure in stream makes spawned sync future not Send
+  let (db, gh, date) = (&state.db, &state.gh, &date);
+  let skip_fork_traffic = state.skip_fork_traffic;
+  let tasks = repos
+    .iter()
+    .map(|repo| {
+      // traffic of watched repos not available (requires push access), nor without token
+      let skip_traffic = (skip_fork_traffic && repo.fork) || repo.watched || gh.is_anonymous();
+      sync_one(db, gh, repo, date, skip_traffic)
+    })
+    .collect::<Vec<_>>();
+  let done = stream::iter(tasks).buffer_unordered(state.concurrency).collect::<Vec<_>>().await;
+
+  let new_stars = repos
+    .iter()
+    .filter_map(|x| prev_stars.get(&(x.id as i64)).map(|p| x.stargazers_count as i64 - p))
+    .sum::<i64>();
+
+  tracing::info!(
+    "update_metrics took {:?} for {} repos (concurrency {})",
+    stime.elapsed(),
+    repos.len(),
+    state.concurrency
+  );
+  state.db.update_deltas().await?;
+  *state.last_update.lock().unwrap() = Some(chrono::Utc::now());
+  sync_stars(&state.db, &state.gh).await?;
+  log_gh_stats(&state.gh); // incl. stars sync
+
+  let errors = done.iter().filter(|x| !**x).count();
+  Ok(RunCounts { repos: repos.len(), errors, new_stars })
+}
+
+/// Returns false if repo metrics update failed
+async fn sync_one(
+  db: &DbClient,
+  gh: &GhClient,
+  repo: &Repo,
+  date: &str,
+  skip_traffic: bool,
+) -> bool {
+  match update_repo_metrics(db, gh, repo, date, skip_traffic).await {
+    Err(e) => {
+      tracing::warn!("failed to update metrics for {}: {:?}", repo.full_name, e);
+      false
+    }
+    // Ok(_) => tracing::info!("updated metrics for {}", repo.full_name),
+    Ok(_) => true,
+  }
+}
+
+/// Log GitHub calls outcomes of sync run, slowest endpoints first
+fn log_gh_stats(gh: &GhClient) {
+  let mut stats = gh.take_stats().into_iter().collect::<Vec<_>>();
+  stats.sort_by_key(|x| std::cmp::Reverse(x.1.total_ms));
+
+  let sum = |f: fn(&EndpointStats) -> u32| stats.iter().map(|x| f(&x.1)).sum::<u32>();
+  tracing::info!(
+    "github calls: {} total, {} ok, {} 4xx, {} 5xx, {} failed",
+    sum(|x| x.calls),
+    sum(|x| x.ok),
+    sum(|x| x.client_errors),
+    sum(|x| x.server_errors),
+    sum(|x| x.failed)
+  );
+
+  for (endpoint, x) in &stats {
+    tracing::info!(
+      "github {}: {} calls ({} ok, {} 4xx, {} 5xx, {} failed), total {} ms, avg {} ms",
+      endpoint,
+      x.calls,
+      x.ok,
+      x.client_errors,
+      x.server_errors,
+      x.failed,
+      x.total_ms,
+      x.total_ms / x.calls.max(1) as u128
+    );
+  }
+}
+
+/// POST JSON payload to `GHS_WEBHOOK_URL`
+pub async fn send_webhook(url: &str, payload: &serde_json::Value) -> Res {
+  // slow endpoint should not hold sync loop
+  let client = reqwest::Client::builder().timeout(std::time::Duration::from_secs(10)).build()?;
+  let rep = client.post(url).json(payload).send().await?;
+  if !rep.status().is_success() {
+    anyhow::bail!("webhook responded with {}", rep.status());
+  }
+
+  Ok(())
+}
+
+/// Alert on repos which views of latest day jumped over trailing average (`GHS_SPIKE_ALERT_FACTOR`)
+pub async fn check_traffic_spikes(state: Arc<AppState>) -> Res {
+  let factor = match state.spike_alert_factor {
+    Some(x) => x,
+    None => return Ok(()),
+  };
+
+  // compare with previous week, skip small numbers where any visit looks like spike
+  let spikes = state.db.get_traffic_spikes(factor, 7, 10).await?;
+  let spikes = spikes.into_iter().filter(|x| {
+    state.visibility.is_allowed(x.private)
+      && state.filter.is_included(&x.name, x.fork, x.archived, x.stars as u32)
+  });
+
+  for spike in spikes {
+    // checked every hour, so alert only once per repo per day
+    {
+      let mut alerts = state.spike_alerts.lock().unwrap();
+      if alerts.get(&spike.name) == Some(&spike.date) {
         continue;
       }
-      // Ok(_) => tracing::info!("updated metrics for {}", repo.full_name),
-      Ok(_) => {}
+      alerts.insert(spike.name.clone(), spike.date.clone());
+    }
+
+    tracing::warn!(
+      "traffic spike in {}: {} views on {} (avg {:.1})",
+      spike.name,
+      spike.views_count,
+      spike.date,
+      spike.avg_views
+    );
+
+    if let Some(url) = &state.webhook_url {
+      let payload =
+        serde_json::json!({ "event": "traffic_spike", "factor": factor, "data": spike });
+      if let Err(e) = send_webhook(url, &payload).await {
+        tracing::warn!("failed to send webhook for {}: {:?}", spike.name, e);
+      }
     }
   }
 
-  tracing::info!("update_metrics took {:?} for {} repos", stime.elapsed(), repos.len());
-  state.db.update_deltas().await?;
-  sync_stars(&state.db, &state.gh).await?;
+  Ok(())
+}
+
+pub async fn log_traffic_gaps(db: &DbClient) -> Res {
+  for gap in db.detect_gaps(None).await? {
+    tracing::info!(
+      "traffic gap for {}: {} days missing between {} and {}",
+      gap.name,
+      gap.days,
+      gap.gap_start,
+      gap.gap_end
+    );
+  }
 
   Ok(())
 }
 
-async fn update_repo_metrics(db: &DbClient, gh: &GhClient, repo: &Repo, date: &str) -> Res {
+async fn update_repo_metrics(
+  db: &DbClient,
+  gh: &GhClient,
+  repo: &Repo,
+  date: &str,
+  skip_traffic: bool,
+) -> Res {
   let prs = gh.get_open_pull_requests(&repo.full_name).await?;
-  let views = gh.traffic_views(&repo.full_name).await?;
-  let clones = gh.traffic_clones(&repo.full_name).await?;
-  let referrers = gh.traffic_refs(&repo.full_name).await?;
-
-  let popular_paths = gh.traffic_paths(&repo.full_name).await?;
+  // graphql api is not available in anonymous mode, unknown count is not saved as zero
+  let issues = match gh.is_anonymous() {
+    true => None,
+    false => match gh.get_issue_counts(&repo.full_name).await {
+      Ok(x) => Some(x),
+      Err(e) => {
+        tracing::warn!("failed to get issues count for {}: {:?}", repo.full_name, e);
+        None
+      }
+    },
+  };
 
   db.insert_repo(&repo).await?;
-  db.insert_stats(&repo, date, &prs).await?;
-  db.insert_views(&repo, &views).await?;
-  db.insert_clones(&repo, &clones).await?;
-  db.insert_referrers(&repo, date, &referrers).await?;
-  db.insert_paths(&repo, date, &popular_paths).await?;
+  db.insert_stats(&repo, date, &prs, issues.as_ref()).await?;
+
+  // release downloads are public, so loaded in anonymous mode too
+  let name = &repo.full_name;
+  if !skip_traffic || gh.is_anonymous() {
+    match gh.get_release_download_counts(name).await {
+      Ok(downloads) => db.insert_downloads(&repo, date, downloads).await?,
+      Err(e) => tracing::warn!("failed to get release downloads for {}: {:?}", name, e),
+    }
+  }
+
+  if skip_traffic {
+    return Ok(());
+  }
+
+  // traffic endpoints require push access (403 for read-only repos),
+  // so basic stats above are kept even if some of traffic requests fail
+  match gh.traffic_views(name).await {
+    Ok(views) => db.insert_views(&repo, &views).await?,
+    Err(e) => tracing::warn!("failed to get views for {}: {:?}", name, e),
+  }
+
+  match gh.traffic_clones(name).await {
+    Ok(clones) => db.insert_clones(&repo, &clones).await?,
+    Err(e) => tracing::warn!("failed to get clones for {}: {:?}", name, e),
+  }
+
+  match gh.traffic_refs(name).await {
+    Ok(referrers) => db.insert_referrers(&repo, date, &referrers).await?,
+    Err(e) => tracing::warn!("failed to get referrers for {}: {:?}", name, e),
+  }
+
+  match gh.traffic_paths(name).await {
+    Ok(popular_paths) => db.insert_paths(&repo, date, &popular_paths).await?,
+    Err(e) => tracing::warn!("failed to get popular paths for {}: {:?}", name, e),
+  }
 
   Ok(())
 }
 
-/// Get stars history for a repo
+/// Get stars history for a repo, `None` if pages budget spent before it started
 /// vec![(date_str, acc_stars, new_stars)), ...]
-pub async fn get_stars_history(gh: &GhClient, repo: &str) -> Res<Vec<(String, u32, u32)>> {
-  let stars = gh.get_stars(repo).await?;
+pub async fn get_stars_history(
+  gh: &GhClient,
+  repo: &str,
+  permits: &Semaphore,
+  pages: &AtomicU32,
+  budget: u32,
+) -> Res<Option<Vec<(String, u32, u32)>>> {
+  let Some(stars) = gh.get_stars(repo, permits, pages, budget).await? else {
+    return Ok(None);
+  };
 
   let mut dat: HashMap<String, u32> = HashMap::new();
   for star in stars {
-    let date = star.starred_at.split("T").next().unwrap().to_owned();
-    let date = format!("{date}T00:00:00Z"); // db stores dates as UTC midnight
+    let date = day_key(&star.starred_at);
     dat.entry(date).and_modify(|e| *e += 1).or_insert(1);
   }
 
@@ -109,53 +584,126 @@ pub async fn get_stars_history(gh: &GhClient, repo: &str) -> Res<Vec<(String, u3
     rs.push((date.clone(), acc_count, new_count.clone()));
   }
 
-  Ok(rs)
+  Ok(Some(rs))
 }
 
 pub async fn sync_stars(db: &DbClient, gh: &GhClient) -> Res {
-  let mut pages_collected = 0;
+  // gh api rate limit is 5000 req/h, so by default this code will do up to 1000 req/h
+  // to not block other possible user pipelines
+  let budget = std::env::var("GHS_STARS_SYNC_BUDGET").unwrap_or_default();
+  let budget = budget.parse::<u32>().unwrap_or(1000);
+
+  // repos & their pages loaded in parallel, permits limit requests in flight across all repos
+  let concurrency = std::env::var("GHS_STARS_CONCURRENCY").unwrap_or_default();
+  let concurrency = concurrency.parse::<usize>().ok().filter(|x| *x > 0).unwrap_or(4);
+  let permits = Semaphore::new(concurrency);
 
   let repos = db.repos_to_sync().await?;
-  for repo in repos {
-    let stime = std::time::Instant::now();
-    // tracing::info!("sync_stars for {}", repo.name);
+  if repos.is_empty() {
+    return Ok(());
+  }
 
-    let stars = match get_stars_history(gh, &repo.name).await {
-      Ok(stars) => stars,
-      Err(e) => {
-        tracing::warn!("failed to get stars for {}: {:?}", repo.name, e);
-        break;
-      }
-    };
+  tracing::info!(
+    "sync_stars for {} repos, budget {} pages per run (concurrency {})",
+    repos.len(),
+    budget,
+    concurrency
+  );
 
-    db.insert_stars(repo.id, &stars).await?;
-    db.mark_repo_stars_synced(repo.id).await?;
+  // repo started only while budget left, so it can be exceeded by repos already in progress
+  let pages_collected = AtomicU32::new(0);
+  let failed = AtomicBool::new(false);
+  let stime = std::time::Instant::now();
 
-    let stars_count = stars.iter().map(|(_, _, c)| c).sum::<u32>();
-    tracing::info!(
-      "sync_stars for {} done in {:?}, {stars_count} starts added",
-      repo.name,
-      stime.elapsed(),
-    );
+  // futures collected upfront: lazy map closure in stream makes spawned sync future not Send
+  let (pages, failed) = (&pages_collected, &failed);
+  let tasks = repos
+    .iter()
+    .map(|repo| sync_repo_stars(db, gh, repo, &permits, pages, failed, budget))
+    .collect::<Vec<_>>();
 
-    // gh api rate limit is 5000 req/h, so this code will do up to 1000 req/h
-    // to not block other possible user pipelines
-    pages_collected += (stars_count + 99) / 100;
-    if pages_collected > 1000 {
-      tracing::info!("sync_stars: {} pages collected, will continue next hour", pages_collected);
-      break;
-    }
+  let results = stream::iter(tasks).buffer_unordered(concurrency).collect::<Vec<_>>().await;
+  let synced = results.into_iter().collect::<Res<Vec<_>>>()?;
+  let synced = synced.into_iter().filter(|x| *x).count();
+
+  let (pages, elapsed) = (pages_collected.load(Ordering::Relaxed), stime.elapsed());
+  tracing::info!(
+    "sync_stars: {} repos, {} pages in {:?} ({:.1} pages/s)",
+    synced,
+    pages,
+    elapsed,
+    pages as f64 / elapsed.as_secs_f64().max(0.001)
+  );
+
+  if synced < repos.len() {
+    tracing::info!("sync_stars: {} repos left, will continue next hour", repos.len() - synced);
   }
 
   Ok(())
 }
 
-#[derive(Debug)]
+/// Load & save stars history of one repo, `Ok(false)` if skipped (budget spent or rate limited)
+async fn sync_repo_stars(
+  db: &DbClient,
+  gh: &GhClient,
+  repo: &RepoItem,
+  permits: &Semaphore,
+  pages: &AtomicU32,
+  failed: &AtomicBool,
+  budget: u32,
+) -> Res<bool> {
+  if failed.load(Ordering::Relaxed) || pages.load(Ordering::Relaxed) >= budget {
+    return Ok(false);
+  }
+
+  let rtime = std::time::Instant::now();
+  let stars = match get_stars_history(gh, &repo.name, permits, pages, budget).await {
+    Ok(Some(stars)) => stars,
+    Ok(None) => return Ok(false),
+    Err(e) => {
+      // most likely rate limit, so no new repos started in this run
+      tracing::warn!("failed to get stars for {}: {:?}", repo.name, e);
+      failed.store(true, Ordering::Relaxed);
+      return Ok(false);
+    }
+  };
+
+  db.insert_stars(repo.id, &stars).await?;
+  db.mark_repo_stars_synced(repo.id).await?;
+
+  let stars_count = stars.iter().map(|(_, _, c)| c).sum::<u32>();
+  tracing::info!(
+    "sync_stars for {} done in {:?}, {stars_count} starts added",
+    repo.name,
+    rtime.elapsed(),
+  );
+
+  Ok(true)
+}
+
+fn ser_regexes<S: serde::Serializer>(items: &[Regex], ser: S) -> Result<S::Ok, S::Error> {
+  ser.collect_seq(items.iter().map(|x| x.as_str()))
+}
+
+// `foo/*` matches any repo of `foo` owner (but not `fooo/bar`)
+fn is_wildcard_match(rule: &str, repo: &str) -> bool {
+  match rule.strip_suffix("/*") {
+    Some(owner) => repo.split_once('/').map(|(x, _)| x == owner).unwrap_or(false),
+    None => false,
+  }
+}
+
+#[derive(Debug, serde::Serialize)]
 pub struct GhsFilter {
   pub include_repos: Vec<String>,
   pub exclude_repos: Vec<String>,
+  #[serde(serialize_with = "ser_regexes")]
+  pub include_regex: Vec<Regex>,
+  #[serde(serialize_with = "ser_regexes")]
+  pub exclude_regex: Vec<Regex>,
   pub exclude_forks: bool,
   pub exclude_archs: bool,
+  pub min_stars: u32,
   pub default_all: bool,
 }
 
@@ -164,15 +712,35 @@ impl GhsFilter {
     let mut default_all = false;
     let mut exclude_forks = false;
     let mut exclude_archs = false;
-    let mut include_repos: Vec<&str> = Vec::new();
-    let mut exclude_repos: Vec<&str> = Vec::new();
+    let mut min_stars = 0;
+    let mut include_repos: Vec<String> = Vec::new();
+    let mut exclude_repos: Vec<String> = Vec::new();
+    let mut include_regex: Vec<Regex> = Vec::new();
+    let mut exclude_regex: Vec<Regex> = Vec::new();
 
-    let rules = rules.trim().to_lowercase();
-    for rule in rules.split(",").map(|x| x.trim()) {
+    for rule in rules.trim().split(",").map(|x| x.trim()) {
       if rule.is_empty() {
         continue;
       }
 
+      // regex rules keep original case, but matched case-insensitive
+      let (negate, pattern) = match rule.strip_prefix('!') {
+        Some(x) => (true, x),
+        None => (false, rule),
+      };
+
+      if let Some(pattern) = pattern.strip_prefix("re:") {
+        match RegexBuilder::new(pattern).case_insensitive(true).build() {
+          Ok(re) if negate => exclude_regex.push(re),
+          Ok(re) => include_regex.push(re),
+          Err(e) => tracing::warn!("invalid regex rule {}: {}", rule, e),
+        }
+        continue;
+      }
+
+      let rule = rule.to_lowercase();
+      let rule = rule.as_str();
+
       if rule == "*" {
         default_all = true;
         continue;
@@ -188,32 +756,48 @@ impl GhsFilter {
         continue;
       }
 
+      if let Some(val) = rule.strip_prefix(">stars:") {
+        match val.parse::<u32>() {
+          Ok(val) => min_stars = val,
+          Err(_) => tracing::warn!("invalid stars rule: {}", rule),
+        }
+        continue;
+      }
+
       if rule.matches('/').count() != 1 {
+        tracing::warn!("invalid filter rule: {}", rule);
         continue;
       }
 
       if rule.starts_with('!') {
-        exclude_repos.push(rule.strip_prefix('!').unwrap());
+        exclude_repos.push(rule.strip_prefix('!').unwrap().to_string());
       } else {
-        include_repos.push(rule);
+        include_repos.push(rule.to_string());
       }
     }
 
     // if no repo rules, include all by default
-    if exclude_repos.is_empty() && include_repos.is_empty() {
+    if exclude_repos.is_empty()
+      && include_repos.is_empty()
+      && exclude_regex.is_empty()
+      && include_regex.is_empty()
+    {
       default_all = true;
     }
 
     Self {
-      include_repos: include_repos.into_iter().map(|x| x.to_string()).collect(),
-      exclude_repos: exclude_repos.into_iter().map(|x| x.to_string()).collect(),
+      include_repos,
+      exclude_repos,
+      include_regex,
+      exclude_regex,
       exclude_forks,
       exclude_archs,
+      min_stars,
       default_all,
     }
   }
 
-  pub fn is_included(&self, repo: &str, is_fork: bool, is_arch: bool) -> bool {
+  pub fn is_included(&self, repo: &str, is_fork: bool, is_arch: bool, stars: u32) -> bool {
     let repo = repo.trim().to_lowercase();
     if repo.is_empty()
       || repo.matches('/').count() != 1
@@ -223,24 +807,32 @@ impl GhsFilter {
       return false;
     }
 
-    for (flag, rules) in vec![(false, &self.exclude_repos), (true, &self.include_repos)] {
-      for rule in rules {
-        if rule == &repo {
-          return flag;
-        }
+    // Rules precedence (order of rules in the filter string does not matter):
+    // 1. any exclude (`!foo/bar`, `!foo/*`, `!re:...`) – wins over every include
+    // 2. exact include (`foo/bar`) – wins over meta flags
+    // 3. wildcard / regex include (`foo/*`, `re:...`) – skipped for forks / archived / unpopular
+    // 4. meta flags (`!fork`, `!archived`, `>stars:N`)
+    // 5. default (include all if no repo rules given)
 
-        // skip wildcards for forks / archived
-        if (self.exclude_forks && is_fork) || (self.exclude_archs && is_arch) {
-          continue;
-        }
+    if self.exclude_repos.iter().any(|x| *x == repo || is_wildcard_match(x, &repo))
+      || self.exclude_regex.iter().any(|re| re.is_match(&repo))
+    {
+      return false;
+    }
 
-        if rule.ends_with("/*")
-          && repo.starts_with(&rule[..rule.len() - 2])
-          && repo.chars().nth(rule.len() - 2) == Some('/')
-        {
-          return flag;
-        }
-      }
+    if self.include_repos.contains(&repo) {
+      return true;
+    }
+
+    // skip wildcards & regexes for forks / archived / unpopular
+    let skip_wildcards =
+      (self.exclude_forks && is_fork) || (self.exclude_archs && is_arch) || stars < self.min_stars;
+
+    if !skip_wildcards
+      && (self.include_repos.iter().any(|x| is_wildcard_match(x, &repo))
+        || self.include_regex.iter().any(|re| re.is_match(&repo)))
+    {
+      return true;
     }
 
     if self.exclude_forks && is_fork {
@@ -251,115 +843,280 @@ impl GhsFilter {
       return false;
     }
 
+    if stars < self.min_stars {
+      return false;
+    }
+
     return self.default_all;
   }
+
+  /// Exact repo rules (not wildcards / regexes) which match none of given repos, to spot typos
+  pub fn unmatched_rules(&self, repos: &[&str]) -> Vec<String> {
+    let repos = repos.iter().map(|x| x.trim().to_lowercase()).collect::<HashSet<_>>();
+    let rules = vec![("", &self.include_repos), ("!", &self.exclude_repos)];
+
+    let mut unmatched = vec![];
+    for (prefix, rules) in rules {
+      for rule in rules.iter().filter(|x| !x.ends_with("/*")) {
+        if !repos.contains(rule) {
+          unmatched.push(format!("{}{}", prefix, rule));
+        }
+      }
+    }
+
+    unmatched
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_parse_listen_addr() {
+    assert_eq!(parse_listen_addr("127.0.0.1", "8080").unwrap(), "127.0.0.1:8080");
+    assert_eq!(parse_listen_addr("0.0.0.0", " 80 ").unwrap(), "0.0.0.0:80");
+    assert_eq!(parse_listen_addr("::", "8080").unwrap(), "[::]:8080");
+    assert_eq!(parse_listen_addr("[::]", "8080").unwrap(), "[::]:8080");
+    assert_eq!(parse_listen_addr("[::1]", "8080").unwrap(), "[::1]:8080");
+    assert_eq!(parse_listen_addr("localhost", "8080").unwrap(), "localhost:8080");
+    assert_eq!(parse_listen_addr("ghstats.local", "1").unwrap(), "ghstats.local:1");
+
+    assert!(parse_listen_addr("127.0.0.1", "").is_err());
+    assert!(parse_listen_addr("127.0.0.1", "abc").is_err());
+    assert!(parse_listen_addr("127.0.0.1", "65536").is_err());
+    assert!(parse_listen_addr("127.0.0.1", "-1").is_err());
+    assert!(parse_listen_addr("", "8080").is_err());
+    assert!(parse_listen_addr("foo bar", "8080").is_err());
+    assert!(parse_listen_addr("foo:8080", "8080").is_err());
+    assert!(parse_listen_addr("-foo", "8080").is_err());
+  }
+
+  #[test]
+  fn test_filter_unmatched_rules() {
+    let filter = GhsFilter::new("foo/bar,foo/typo,!foo/baz,!foo/gone,bar/*,re:^foo/");
+    let repos = vec!["Foo/Bar", "foo/baz", "bar/abc"];
+    assert_eq!(filter.unmatched_rules(&repos), vec!["foo/typo", "!foo/gone"]);
+
+    let filter = GhsFilter::new("*,!fork");
+    assert!(filter.unmatched_rules(&repos).is_empty());
+  }
+
+  #[test]
+  fn test_day_key() {
+    assert_eq!(day_key("2024-01-02T00:00:00Z"), "2024-01-02T00:00:00Z");
+    assert_eq!(day_key("2024-01-02T23:59:59Z"), "2024-01-02T00:00:00Z");
+    assert_eq!(day_key("2024-01-02T23:59:59.123456+00:00"), "2024-01-02T00:00:00Z");
+    assert_eq!(day_key("2024-01-02T23:30:00-02:00"), "2024-01-03T00:00:00Z");
+    assert_eq!(day_key("2024-01-02T01:00:00+03:00"), "2024-01-01T00:00:00Z");
+    assert_eq!(day_key("2024-01-02"), "2024-01-02T00:00:00Z");
+
+    // same key for sync date and star date of same UTC day
+    let now = chrono::Utc::now();
+    let starred_at = now.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    assert_eq!(day_key(&now.to_rfc3339()), day_key(&starred_at));
+  }
+
+  #[test]
+  fn test_pin_first() {
+    let items = vec!["a/1", "a/2", "b/3", "b/4"];
+    let pinned = vec!["b/4".to_string(), "x/y".to_string(), "A/2".to_string()];
+
+    let (items, count) = pin_first(items, &pinned, |x| x);
+    assert_eq!(items, vec!["b/4", "a/2", "a/1", "b/3"]);
+    assert_eq!(count, 2);
+
+    let (items, count) = pin_first(items, &[], |x| x);
+    assert_eq!(items, vec!["b/4", "a/2", "a/1", "b/3"]);
+    assert_eq!(count, 0);
+  }
+
+  #[test]
+  fn test_render_badge() {
+    let svg = render_badge("stars", "1,234", "#007ec6");
+    assert!(svg.contains(r#"aria-label="stars: 1,234""#));
+    assert!(svg.contains(">stars</text>"));
+
+    let svg = render_badge("<script>alert(1)</script>", "a&\"b'", "#007ec6");
+    assert!(!svg.contains("<script>"));
+    assert!(svg.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+    assert!(svg.contains("a&amp;&quot;b&apos;"));
+  }
+
+  #[test]
+  fn test_url_encode() {
+    assert_eq!(url_encode("cli"), "cli");
+    assert_eq!(url_encode("a-b_c.d~e"), "a-b_c.d~e");
+    assert_eq!(url_encode("a&b=c d"), "a%26b%3Dc%20d");
+    assert_eq!(url_encode("\"><x>"), "%22%3E%3Cx%3E");
+    assert_eq!(url_encode("é"), "%C3%A9");
+  }
+
+  #[test]
+  fn test_truncate_middle() {
+    assert_eq!(truncate_middle("hello", 10), "hello");
+    assert_eq!(truncate_middle("hello world!", 9), "hel...ld!");
+    assert_eq!(truncate_middle("привет мир, как дела", 9), "при...ела");
+    assert_eq!(truncate_middle("🦀🦀🦀🦀🦀🦀🦀🦀🦀🦀", 7), "🦀🦀...🦀🦀");
+    assert_eq!(truncate_middle("日本語のテキスト", 8), "日本語のテキスト");
+    assert_eq!(truncate_middle("abcdef", 2), "...");
+  }
+
+  #[test]
+  fn test_parse_periods() {
+    let days = |x: &str| parse_periods(x).iter().map(|x| x.0).collect::<Vec<_>>();
+    assert_eq!(days(""), vec![7, 14, 30, 90, -1]);
+    assert_eq!(days("7,30,365"), vec![7, 30, 365, -1]);
+    assert_eq!(days(" 365, 30 ,30,abc,-5,0"), vec![30, 365, -1]);
+    assert_eq!(days("abc"), vec![7, 14, 30, 90, -1]);
+
+    let periods = parse_periods("365");
+    assert_eq!(periods[0], (365, "Last 365 days".to_string()));
+    assert_eq!(periods[1], (-1, "All time".to_string()));
+  }
+
+  #[test]
+  fn test_parse_columns() {
+    let cols = |x: &str| parse_columns(x).unwrap().join(",");
+    assert_eq!(cols(""), DEFAULT_COLUMNS);
+    assert_eq!(cols("name,stars,views,clones"), "name,stars,views_count,clones_count");
+    assert_eq!(cols(" Name , trend,issues,name,"), "name,trend,issues_open");
+    assert!(parse_columns("name,foo").is_err());
+  }
+
+  #[test]
+  fn test_format_compact() {
+    assert_eq!(format_compact(0), "0");
+    assert_eq!(format_compact(999), "999");
+    assert_eq!(format_compact(1000), "1K");
+    assert_eq!(format_compact(1234), "1.2K");
+    assert_eq!(format_compact(12345), "12.3K");
+    assert_eq!(format_compact(999_999), "1M");
+    assert_eq!(format_compact(1_234_567), "1.2M");
+    assert_eq!(format_compact(2_000_000_000), "2B");
+    assert_eq!(format_compact(-1500), "-1.5K");
+  }
+
+  #[test]
+  fn test_format_size() {
+    assert_eq!(format_size(0), "0 KB");
+    assert_eq!(format_size(512), "512 KB");
+    assert_eq!(format_size(1024), "1.0 MB");
+    assert_eq!(format_size(1536), "1.5 MB");
+    assert_eq!(format_size(5 * 1024 * 1024), "5.0 GB");
+  }
+
+  #[test]
+  fn test_format_ago() {
+    assert_eq!(format_ago(-5), "just now");
+    assert_eq!(format_ago(59), "just now");
+    assert_eq!(format_ago(60), "1 min ago");
+    assert_eq!(format_ago(23 * 60 + 10), "23 min ago");
+    assert_eq!(format_ago(3600), "1 h ago");
+    assert_eq!(format_ago(86400 * 3), "3 d ago");
+  }
+
   #[test]
   fn test_empty_fitler() {
     let r = &GhsFilter::new("");
 
-    assert!(r.is_included("foo/bar", false, false));
-    assert!(r.is_included("foo/baz", false, false));
-    assert!(r.is_included("abc/123", false, false));
-    assert!(r.is_included("abc/xyz-123", false, false));
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(r.is_included("foo/baz", false, false, 0));
+    assert!(r.is_included("abc/123", false, false, 0));
+    assert!(r.is_included("abc/xyz-123", false, false, 0));
 
     // exclude invalid names
-    assert!(!r.is_included("foo/", false, false));
-    assert!(!r.is_included("/bar", false, false));
-    assert!(!r.is_included("foo", false, false));
-    assert!(!r.is_included("foo/bar/baz", false, false));
+    assert!(!r.is_included("foo/", false, false, 0));
+    assert!(!r.is_included("/bar", false, false, 0));
+    assert!(!r.is_included("foo", false, false, 0));
+    assert!(!r.is_included("foo/bar/baz", false, false, 0));
 
     // include forks / archived
-    assert!(r.is_included("foo/bar", true, false));
-    assert!(r.is_included("foo/bar", false, true));
-    assert!(r.is_included("foo/bar", true, true));
+    assert!(r.is_included("foo/bar", true, false, 0));
+    assert!(r.is_included("foo/bar", false, true, 0));
+    assert!(r.is_included("foo/bar", true, true, 0));
   }
 
   #[test]
   fn test_filter_names() {
     let r = &GhsFilter::new("foo/*,abc/xyz");
 
-    assert!(r.is_included("foo/bar", false, false));
-    assert!(r.is_included("foo/123", false, false));
-    assert!(r.is_included("abc/xyz", false, false));
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(r.is_included("foo/123", false, false, 0));
+    assert!(r.is_included("abc/xyz", false, false, 0));
 
-    assert!(!r.is_included("foo/bar/baz", false, false));
-    assert!(!r.is_included("abc/123", false, false));
+    assert!(!r.is_included("foo/bar/baz", false, false, 0));
+    assert!(!r.is_included("abc/123", false, false, 0));
 
     // include forks / archived
-    assert!(r.is_included("foo/bar", true, false));
-    assert!(r.is_included("foo/bar", false, true));
+    assert!(r.is_included("foo/bar", true, false, 0));
+    assert!(r.is_included("foo/bar", false, true, 0));
 
     // exact org/user match
     let r = &GhsFilter::new("foo/*");
-    assert!(!r.is_included("fooo/bar", false, false));
+    assert!(!r.is_included("fooo/bar", false, false, 0));
   }
 
   #[test]
   fn test_filter_names_case() {
     let r = &GhsFilter::new("foo/*,abc/xyz");
-    assert!(r.is_included("FOO/BAR", false, false));
-    assert!(r.is_included("Foo/Bar", false, false));
+    assert!(r.is_included("FOO/BAR", false, false, 0));
+    assert!(r.is_included("Foo/Bar", false, false, 0));
 
     let r = &GhsFilter::new("FOO/*,Abc/XYZ");
-    assert!(r.is_included("foo/bar", false, false));
-    assert!(r.is_included("foo/baz", false, false));
-    assert!(r.is_included("abc/xyz", false, false));
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(r.is_included("foo/baz", false, false, 0));
+    assert!(r.is_included("abc/xyz", false, false, 0));
   }
 
   #[test]
   fn test_filter_all_expect() {
     let r = &GhsFilter::new("*");
-    assert!(r.is_included("foo/bar", false, false));
-    assert!(r.is_included("abc/123", false, false));
-    assert!(r.is_included("abc/123", true, false));
-    assert!(r.is_included("abc/123", true, true));
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(r.is_included("abc/123", false, false, 0));
+    assert!(r.is_included("abc/123", true, false, 0));
+    assert!(r.is_included("abc/123", true, true, 0));
 
     let r = &GhsFilter::new("-*"); // single rule invalid, include all
-    assert!(r.is_included("foo/bar", false, false));
-    assert!(r.is_included("abc/123", false, false));
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(r.is_included("abc/123", false, false, 0));
 
     let r = &GhsFilter::new("*,!foo/bar,!abc/123");
-    assert!(!r.is_included("foo/bar", false, false));
-    assert!(!r.is_included("abc/123", false, false));
-    assert!(r.is_included("foo/baz", false, false));
-    assert!(r.is_included("abc/xyz", false, false));
+    assert!(!r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("abc/123", false, false, 0));
+    assert!(r.is_included("foo/baz", false, false, 0));
+    assert!(r.is_included("abc/xyz", false, false, 0));
 
     let r = &GhsFilter::new("*,!foo/*");
-    assert!(!r.is_included("foo/bar", false, false));
-    assert!(!r.is_included("foo/baz", false, false));
-    assert!(r.is_included("abc/123", false, false));
-    assert!(r.is_included("abc/xyz", false, false));
+    assert!(!r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("foo/baz", false, false, 0));
+    assert!(r.is_included("abc/123", false, false, 0));
+    assert!(r.is_included("abc/xyz", false, false, 0));
   }
 
   #[test]
   fn test_filter_names_only() {
     let r = &GhsFilter::new("foo/*,!foo/bar");
-    assert!(!r.is_included("abc/xyz", false, false));
-    assert!(!r.is_included("foo/bar", false, false));
-    assert!(!r.is_included("FOO/Bar", false, false));
+    assert!(!r.is_included("abc/xyz", false, false, 0));
+    assert!(!r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("FOO/Bar", false, false, 0));
 
-    assert!(r.is_included("foo/abc", false, false));
-    assert!(r.is_included("foo/abc", true, false));
-    assert!(r.is_included("foo/abc", true, true));
+    assert!(r.is_included("foo/abc", false, false, 0));
+    assert!(r.is_included("foo/abc", true, false, 0));
+    assert!(r.is_included("foo/abc", true, true, 0));
 
     let r = &GhsFilter::new("foo/*,!foo/bar,!foo/baz,abc/xyz");
-    assert!(!r.is_included("foo/bar", false, false));
-    assert!(!r.is_included("foo/baz", false, false));
-    assert!(!r.is_included("abc/123", false, false));
+    assert!(!r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("foo/baz", false, false, 0));
+    assert!(!r.is_included("abc/123", false, false, 0));
 
-    assert!(r.is_included("foo/123", false, false));
-    assert!(r.is_included("foo/123", true, false));
-    assert!(r.is_included("foo/123", false, true));
+    assert!(r.is_included("foo/123", false, false, 0));
+    assert!(r.is_included("foo/123", true, false, 0));
+    assert!(r.is_included("foo/123", false, true, 0));
 
-    assert!(r.is_included("abc/xyz", false, false));
-    assert!(r.is_included("abc/xyz", true, false));
-    assert!(r.is_included("abc/xyz", false, true));
+    assert!(r.is_included("abc/xyz", false, false, 0));
+    assert!(r.is_included("abc/xyz", true, false, 0));
+    assert!(r.is_included("abc/xyz", false, true, 0));
   }
 
   #[test]
@@ -369,29 +1126,29 @@ mod tests {
     assert!(r.exclude_archs);
     assert!(r.default_all);
 
-    assert!(r.is_included("foo/bar", false, false));
-    assert!(!r.is_included("foo/bar", true, false));
-    assert!(!r.is_included("foo/bar", false, true));
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("foo/bar", true, false, 0));
+    assert!(!r.is_included("foo/bar", false, true, 0));
 
-    assert!(r.is_included("abc/123", false, false));
-    assert!(!r.is_included("abc/123", true, false));
-    assert!(!r.is_included("abc/123", false, true));
+    assert!(r.is_included("abc/123", false, false, 0));
+    assert!(!r.is_included("abc/123", true, false, 0));
+    assert!(!r.is_included("abc/123", false, true, 0));
 
     // explicitly added
-    assert!(r.is_included("foo/baz", false, false));
-    assert!(r.is_included("foo/baz", true, false));
-    assert!(r.is_included("foo/baz", false, true));
+    assert!(r.is_included("foo/baz", false, false, 0));
+    assert!(r.is_included("foo/baz", true, false, 0));
+    assert!(r.is_included("foo/baz", false, true, 0));
   }
 
   #[test]
   fn test_filter_meta_wildcard() {
     let r = &GhsFilter::new("!fork,abc/*,abc/xyz");
-    assert!(!r.is_included("abc/123", true, false)); // no wildcard for forks
-    assert!(r.is_included("abc/xyz", true, false)); // explicitly added
+    assert!(!r.is_included("abc/123", true, false, 0)); // no wildcard for forks
+    assert!(r.is_included("abc/xyz", true, false, 0)); // explicitly added
 
     let r = &GhsFilter::new("!archived,abc/*,abc/xyz");
-    assert!(!r.is_included("abc/123", false, true)); // no wildcard for archived
-    assert!(r.is_included("abc/xyz", false, true)); // explicitly added
+    assert!(!r.is_included("abc/123", false, true, 0)); // no wildcard for archived
+    assert!(r.is_included("abc/xyz", false, true, 0)); // explicitly added
   }
 
   #[test]
@@ -400,17 +1157,148 @@ mod tests {
     let rules = vec!["foo/*,!foo/bar", "!foo/bar,foo/*"];
     for r in rules {
       let r = &GhsFilter::new(r);
-      assert!(!r.is_included("foo/bar", false, false)); // explicitly excluded
-      assert!(!r.is_included("abc/abc", false, false)); // not included by default
-      assert!(r.is_included("foo/baz", false, false)); // wildcard included
+      assert!(!r.is_included("foo/bar", false, false, 0)); // explicitly excluded
+      assert!(!r.is_included("abc/abc", false, false, 0)); // not included by default
+      assert!(r.is_included("foo/baz", false, false, 0)); // wildcard included
     }
 
     let rules = vec!["foo/*,!fork", "!fork,foo/*"];
     for r in rules {
       let r = &GhsFilter::new(r);
-      assert!(r.is_included("foo/bar", false, false)); // wildcard included
-      assert!(!r.is_included("foo/bar", true, false)); // forks excluded
-      assert!(!r.is_included("abc/abc", false, false)); // not included by default
+      assert!(r.is_included("foo/bar", false, false, 0)); // wildcard included
+      assert!(!r.is_included("foo/bar", true, false, 0)); // forks excluded
+      assert!(!r.is_included("abc/abc", false, false, 0)); // not included by default
+    }
+  }
+
+  #[test]
+  fn test_filter_negation_first() {
+    let rules = vec!["!foo/*,abc/*", "abc/*,!foo/*"];
+    for r in rules {
+      let r = &GhsFilter::new(r);
+      assert!(!r.default_all);
+      assert!(!r.is_included("foo/bar", false, false, 0)); // wildcard excluded
+      assert!(r.is_included("abc/bar", false, false, 0)); // wildcard included
+      assert!(!r.is_included("xyz/bar", false, false, 0)); // not included by default
+    }
+
+    // exclude wildcard wins over include wildcard for same owner
+    let rules = vec!["!foo/*,foo/*", "foo/*,!foo/*", "*,!foo/*", "!foo/*,*"];
+    for r in rules {
+      let r = &GhsFilter::new(r);
+      assert!(!r.is_included("foo/bar", false, false, 0));
+      assert!(!r.is_included("foo/bar", true, true, 100));
+    }
+
+    // exclude wildcard wins over include regex
+    let rules = vec!["!foo/*,re:^foo/", "re:^foo/,!foo/*"];
+    for r in rules {
+      let r = &GhsFilter::new(r);
+      assert!(!r.is_included("foo/bar", false, false, 0));
+    }
+
+    // exclude wildcard & regex win over exact include, also for forks
+    let rules =
+      vec!["!foo/*,foo/bar", "foo/bar,!foo/*", "foo/bar,!re:^foo/", "!fork,foo/bar,!foo/*"];
+    for r in rules {
+      let r = &GhsFilter::new(r);
+      assert!(!r.is_included("foo/bar", false, false, 0));
+      assert!(!r.is_included("foo/bar", true, false, 0));
+    }
+
+    // exact exclude wins over exact include
+    let rules = vec!["!foo/bar,foo/bar", "foo/bar,!foo/bar"];
+    for r in rules {
+      let r = &GhsFilter::new(r);
+      assert!(!r.is_included("foo/bar", false, false, 0));
+    }
+
+    // meta flags still applied to wildcard includes
+    let rules = vec!["!foo/*,!fork,abc/*", "abc/*,!fork,!foo/*"];
+    for r in rules {
+      let r = &GhsFilter::new(r);
+      assert!(r.is_included("abc/bar", false, false, 0));
+      assert!(!r.is_included("abc/bar", true, false, 0));
+      assert!(!r.is_included("foo/bar", false, false, 0));
+    }
+  }
+
+  #[test]
+  fn test_filter_min_stars() {
+    let r = &GhsFilter::new(">stars:50");
+    assert_eq!(r.min_stars, 50);
+    assert!(r.default_all);
+
+    assert!(!r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("foo/bar", false, false, 49));
+    assert!(r.is_included("foo/bar", false, false, 50));
+    assert!(r.is_included("foo/bar", false, false, 51));
+
+    let r = &GhsFilter::new("foo/*,abc/xyz,>stars:10");
+    assert!(!r.is_included("foo/bar", false, false, 9)); // no wildcard for unpopular
+    assert!(r.is_included("foo/bar", false, false, 10));
+    assert!(r.is_included("abc/xyz", false, false, 0)); // explicitly added
+    assert!(!r.is_included("abc/123", false, false, 100));
+
+    let r = &GhsFilter::new(">stars:abc"); // invalid rule, ignored
+    assert_eq!(r.min_stars, 0);
+    assert!(r.is_included("foo/bar", false, false, 0));
+  }
+
+  #[test]
+  fn test_filter_regex() {
+    let r = &GhsFilter::new("re:^foo/test-.*");
+    assert!(!r.default_all);
+    assert!(r.is_included("foo/test-abc", false, false, 0));
+    assert!(r.is_included("Foo/Test-ABC", false, false, 0));
+    assert!(!r.is_included("foo/abc-test", false, false, 0));
+    assert!(!r.is_included("bar/test-abc", false, false, 0));
+
+    let r = &GhsFilter::new("*,!re:-test$");
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("foo/bar-test", false, false, 0));
+    assert!(!r.is_included("abc/xyz-TEST", false, false, 0));
+
+    // mixed with ordinary rules
+    let r = &GhsFilter::new("foo/*,abc/xyz,!re:^foo/tmp-,re:^bar/(a|b)$");
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("foo/tmp-1", false, false, 0));
+    assert!(r.is_included("abc/xyz", false, false, 0));
+    assert!(!r.is_included("abc/123", false, false, 0));
+    assert!(r.is_included("bar/a", false, false, 0));
+    assert!(r.is_included("bar/b", false, false, 0));
+    assert!(!r.is_included("bar/c", false, false, 0));
+
+    // regex exclusion wins over exact match
+    let r = &GhsFilter::new("*,!re:^foo/,foo/bar");
+    assert!(!r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("foo/baz", false, false, 0));
+    assert!(r.is_included("bar/baz", false, false, 0));
+
+    // no regex for forks / archived
+    let r = &GhsFilter::new("!fork,re:^foo/");
+    assert!(r.is_included("foo/bar", false, false, 0));
+    assert!(!r.is_included("foo/bar", true, false, 0));
+
+    // invalid regex skipped
+    let r = &GhsFilter::new("re:foo/(bar");
+    assert!(r.include_regex.is_empty());
+    assert!(r.default_all);
+  }
+
+  #[test]
+  fn test_filter_plain_rules_unchanged() {
+    // regex support is additive: plain rules keep exclude-first behavior
+    for r in ["!foo/*,foo/bar", "foo/bar,!foo/*"] {
+      let r = &GhsFilter::new(r);
+      assert!(r.include_regex.is_empty() && r.exclude_regex.is_empty());
+      assert!(!r.is_included("foo/bar", false, false, 0));
+      assert!(!r.is_included("foo/baz", false, false, 0));
+      assert!(!r.is_included("abc/xyz", false, false, 0));
     }
+
+    let r = &GhsFilter::new("foo/*,!foo/bar");
+    assert!(!r.is_included("foo/bar", false, false, 0));
+    assert!(r.is_included("foo/baz", false, false, 0));
   }
 }
@@ -1,6 +1,12 @@
 use std::sync::Arc;
 
-use axum::{response::IntoResponse, routing::get, Router};
+use axum::{
+  extract::{Query, State},
+  response::IntoResponse,
+  routing::get,
+  Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
 use db_client::RepoFilter;
 use reqwest::StatusCode;
 use state::AppState;
@@ -17,7 +23,11 @@ mod types;
 mod utils;
 
 async fn check_new_release(state: Arc<AppState>) -> Res {
-  let tag = state.gh.get_latest_release_ver("vladkens/ghstats").await?;
+  let tag = match state.gh.get_latest_release_ver("vladkens/ghstats").await? {
+    Some(x) => x,
+    None => return Ok(()),
+  };
+
   let mut last_tag = state.last_release.lock().unwrap();
   if *last_tag != tag {
     tracing::info!("new release available: {} -> {}", *last_tag, tag);
@@ -27,40 +37,85 @@ async fn check_new_release(state: Arc<AppState>) -> Res {
   Ok(())
 }
 
-async fn start_cron(state: Arc<AppState>) -> Res {
-  use tokio_cron_scheduler::{Job, JobScheduler};
-
-  // note: for development, uncomment to update metrics on start
-  helpers::update_metrics(state.clone()).await?;
+async fn check_rate_limit(state: Arc<AppState>) -> Res {
+  // cleared on failure, so ready probe does not report GitHub by stale value
+  let rate = match state.gh.get_rate_limit().await {
+    Ok(x) => x,
+    Err(e) => {
+      *state.rate_limit.lock().unwrap() = None;
+      return Err(e);
+    }
+  };
+  tracing::info!("github rate limit: {} of {} remaining", rate.remaining, rate.limit);
+  *state.rate_limit.lock().unwrap() = Some(rate);
+  Ok(())
+}
 
+/// Initial load on start, runs once (not retried together with scheduler)
+async fn initial_sync(state: Arc<AppState>) -> Res {
   // if new db, update metrics immediately
+  // also re-request current 14-day traffic window if asked to not wait for cron
   let repos = state.db.get_repos(&RepoFilter::default()).await?;
-  if repos.len() == 0 {
-    tracing::info!("no repos found, load initial metrics");
-    match helpers::update_metrics(state.clone()).await {
-      Err(e) => tracing::error!("failed to update metrics: {:?}", e),
-      Ok(_) => {}
+  let need_sync = repos.len() == 0 || state.sync_on_start;
+
+  match (need_sync, state.skip_initial_sync) {
+    (true, false) => {
+      tracing::info!("load initial metrics");
+      if let Err(e) = helpers::update_metrics(state.clone()).await {
+        tracing::error!("failed to update metrics: {:?}", e);
+      }
+    }
+    (true, true) => {
+      tracing::info!("initial metrics load skipped, will be loaded on first cron tick");
     }
-  } else {
-    state.db.update_deltas().await?;
+    _ => {}
   }
 
+  // sync updates only recent deltas (if configured), so full history recomputed once on start
+  state.db.update_deltas_full().await?;
+
+  helpers::log_traffic_gaps(&state.db).await?;
+  let _ = check_rate_limit(state.clone()).await;
+  if let Err(e) = helpers::check_traffic_spikes(state.clone()).await {
+    tracing::warn!("failed to check traffic spikes: {:?}", e);
+  }
+
+  Ok(())
+}
+
+/// Sync schedule: last minute of every hour (sec min hour day month weekday)
+const CRON_EXPR: &str = "0 59 * * * *";
+
+async fn start_cron(state: Arc<AppState>) -> Res {
+  use tokio_cron_scheduler::{Job, JobScheduler};
+
   // https://docs.github.com/en/rest/using-the-rest-api/rate-limits-for-the-rest-api?apiVersion=2022-11-28
   // >> All of these requests count towards your personal rate limit of 5,000 requests per hour.
 
   // https://docs.github.com/en/repositories/viewing-activity-and-data-for-your-repository/viewing-traffic-to-a-repository
   // >> Full clones and visitor information update hourly, while referring sites and popular content sections update daily.
 
-  // last minute of every hour
-  let job = Job::new_async("0 59 * * * *", move |_, _| {
+  let job = Job::new_async(CRON_EXPR, move |_, _| {
     let state = state.clone();
     Box::pin(async move {
-      let _ = check_new_release(state.clone()).await;
+      if state.update_check {
+        if let Err(e) = check_new_release(state.clone()).await {
+          tracing::warn!("failed to check new release: {:?}", e);
+        }
+      }
 
       match helpers::update_metrics(state.clone()).await {
         Err(e) => tracing::error!("failed to update metrics: {:?}", e),
         Ok(_) => {}
       }
+
+      if let Err(e) = check_rate_limit(state.clone()).await {
+        tracing::warn!("failed to get rate limit: {:?}", e);
+      }
+
+      if let Err(e) = helpers::check_traffic_spikes(state.clone()).await {
+        tracing::warn!("failed to check traffic spikes: {:?}", e);
+      }
     })
   })?;
 
@@ -76,6 +131,54 @@ async fn health() -> impl IntoResponse {
   (StatusCode::OK, axum::response::Json(msg))
 }
 
+#[derive(Debug, serde::Deserialize)]
+struct ReadyQuery {
+  #[serde(default, deserialize_with = "db_client::de_flag")]
+  github: bool,
+}
+
+async fn health_ready(
+  State(state): State<Arc<AppState>>,
+  Query(qs): Query<ReadyQuery>,
+) -> impl IntoResponse {
+  let mut checks = serde_json::Map::new();
+  let mut ok = true;
+
+  let db = match state.db.ping().await {
+    Ok(_) => "ok".to_string(),
+    Err(e) => {
+      ok = false;
+      format!("error: {}", e)
+    }
+  };
+  checks.insert("db".to_string(), db.into());
+
+  // answered from rate limit cached by cron, so probes do not spend GitHub requests
+  if qs.github {
+    let rate = state.rate_limit.lock().unwrap().clone();
+    let gh = match rate {
+      Some(x) if x.remaining > 0 || x.reset <= chrono::Utc::now().timestamp() => "ok".to_string(),
+      Some(x) => {
+        ok = false;
+        format!("error: rate limit exceeded until {}", x.reset)
+      }
+      None => {
+        ok = false;
+        "error: not available".to_string()
+      }
+    };
+    checks.insert("github".to_string(), gh.into());
+  }
+
+  let (code, status) = match ok {
+    true => (StatusCode::OK, "ok"),
+    false => (StatusCode::SERVICE_UNAVAILABLE, "error"),
+  };
+
+  let msg = serde_json::json!({ "status": status, "checks": checks });
+  (code, axum::response::Json(msg))
+}
+
 #[tokio::main]
 async fn main() -> Res {
   dotenvy::dotenv().ok();
@@ -86,19 +189,54 @@ async fn main() -> Res {
 
   let router = Router::new()
     .nest("/api", routes::api_routes())
+    .merge(routes::metrics_routes())
     .merge(routes::html_routes())
     .layer(
       TraceLayer::new_for_http()
         .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
         .on_response(trace::DefaultOnResponse::new().level(Level::INFO)),
     )
-    .route("/health", get(health)); // do not show logs for this route
+    .route("/health", get(health)) // do not show logs for this route
+    .route("/health/ready", get(health_ready));
+
+  let host = std::env::var("HOST").unwrap_or("127.0.0.1".to_string());
+  let port = std::env::var("PORT").unwrap_or("8080".to_string());
+  let addr = match helpers::parse_listen_addr(&host, &port) {
+    Ok(x) => x,
+    Err(e) => {
+      tracing::error!("{}", e);
+      return Err(e);
+    }
+  };
+
+  // optional built-in TLS, checked before start to fail fast on broken cert / key
+  let tls = match (std::env::var("GHS_TLS_CERT").ok(), std::env::var("GHS_TLS_KEY").ok()) {
+    (Some(cert), Some(key)) => {
+      let _ = rustls::crypto::ring::default_provider().install_default();
+      match RustlsConfig::from_pem_file(&cert, &key).await {
+        Ok(x) => Some(x),
+        Err(e) => {
+          tracing::error!("failed to load TLS cert {} / key {}: {}", cert, key, e);
+          return Err(e.into());
+        }
+      }
+    }
+    (None, None) => None,
+    _ => {
+      tracing::error!("both GHS_TLS_CERT and GHS_TLS_KEY must be set to enable TLS");
+      anyhow::bail!("both GHS_TLS_CERT and GHS_TLS_KEY must be set to enable TLS");
+    }
+  };
 
   let state = Arc::new(AppState::new().await?);
   let service = router.with_state(state.clone()).into_make_service();
 
   let cron_state = state.clone();
   tokio::spawn(async move {
+    if let Err(e) = initial_sync(cron_state.clone()).await {
+      tracing::error!("failed initial sync: {:?}", e);
+    }
+
     loop {
       match start_cron(cron_state.clone()).await {
         Err(e) => {
@@ -110,13 +248,32 @@ async fn main() -> Res {
     }
   });
 
-  let host = std::env::var("HOST").unwrap_or("127.0.0.1".to_string());
-  let port = std::env::var("PORT").unwrap_or("8080".to_string());
-  let addr = format!("{}:{}", host, port);
+  let listener = match tokio::net::TcpListener::bind(&addr).await {
+    Ok(x) => x,
+    Err(e) => {
+      tracing::error!("failed to bind {}: {}", addr, e);
+      return Err(e.into());
+    }
+  };
+
+  match tls {
+    Some(config) => {
+      tracing::info!("listening on https://{}", addr);
+      let handle = axum_server::Handle::new();
+      let shutdown = handle.clone();
+      tokio::spawn(async move {
+        utils::shutdown_signal().await;
+        shutdown.graceful_shutdown(None);
+      });
 
-  let listener = tokio::net::TcpListener::bind(&addr).await?;
-  tracing::info!("listening on http://{}", addr);
-  axum::serve(listener, service).with_graceful_shutdown(utils::shutdown_signal()).await?;
+      let listener = listener.into_std()?;
+      axum_server::from_tcp_rustls(listener, config).handle(handle).serve(service).await?;
+    }
+    None => {
+      tracing::info!("listening on http://{}", addr);
+      axum::serve(listener, service).with_graceful_shutdown(utils::shutdown_signal()).await?;
+    }
+  }
 
   Ok(())
 }
@@ -1,10 +1,19 @@
 use std::sync::Arc;
 
-use axum::extract::{Query, Request, State};
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
 use axum::Json;
+use futures::{stream, StreamExt};
 
-use crate::db_client::{RepoFilter, RepoTotals};
-use crate::types::JsonRes;
+use crate::db_client::{
+  Direction, ExportData, ExportHeader, ExportStatsLine, PopularFilter, PopularKind, PopularSort,
+  RepoFilter, RepoMetrics, RepoPopularItem, RepoSort, RepoTotals,
+};
+use crate::gh_client::RateLimit;
+use crate::state::RunStatus;
+use crate::types::{AppError, JsonRes};
 use crate::AppState;
 
 #[derive(Debug, serde::Serialize)]
@@ -14,21 +23,344 @@ pub struct ReposList {
   total_forks: i32,
   total_views: i32,
   total_clones: i32,
+  total_views_uniques: i32,
+  total_clones_uniques: i32,
+  total_issues: i32,
+  total_prs: i32,
+  page: u32,
+  per_page: u32,
+  pages: u32,
   items: Vec<RepoTotals>,
 }
 
 pub async fn api_get_repos(State(state): State<Arc<AppState>>, req: Request) -> JsonRes<ReposList> {
-  let qs: Query<RepoFilter> = Query::try_from_uri(req.uri())?;
+  let qs = state.get_repo_filter(req.uri())?;
   let repos = state.get_repos_filtered(&qs).await?;
 
+  // totals are for all matched repos, items for current page only (all if `per_page` not set)
+  let pages = qs.pages_count(repos.len()) as u32;
+  let (page, per_page) = match qs.per_page {
+    Some(x) if x > 0 => (qs.page.unwrap_or(1).max(1), x),
+    _ => (1, repos.len() as u32),
+  };
+
   let repos_list = ReposList {
     total_count: repos.len() as i32,
     total_stars: repos.iter().map(|r| r.stars).sum(),
     total_forks: repos.iter().map(|r| r.forks).sum(),
     total_views: repos.iter().map(|r| r.views_count).sum(),
     total_clones: repos.iter().map(|r| r.clones_count).sum(),
-    items: repos,
+    total_views_uniques: repos.iter().map(|r| r.views_uniques).sum(),
+    total_clones_uniques: repos.iter().map(|r| r.clones_uniques).sum(),
+    total_issues: repos.iter().map(|r| r.issues_open).sum(),
+    total_prs: repos.iter().map(|r| r.prs).sum(),
+    page,
+    per_page,
+    pages,
+    items: qs.paginate(repos),
   };
 
   Ok(Json(repos_list))
 }
+
+pub async fn api_get_repo(
+  State(state): State<Arc<AppState>>,
+  Path((owner, repo)): Path<(String, String)>,
+) -> JsonRes<RepoTotals> {
+  let repo = format!("{}/{}", owner, repo);
+  let totals = match state.db.get_repo_totals(&repo).await? {
+    Some(x) => x,
+    None => return AppError::not_found(),
+  };
+
+  if !state.filter.is_included(&totals.name, totals.fork, totals.archived, totals.stars as u32) {
+    return AppError::not_found();
+  }
+
+  Ok(Json(totals))
+}
+
+async fn get_popular(
+  state: &AppState,
+  repo: &str,
+  kind: &PopularKind,
+  qs: &PopularFilter,
+) -> JsonRes<Vec<RepoPopularItem>> {
+  match state.db.get_repo_totals(repo).await? {
+    Some(x) if state.filter.is_included(&x.name, x.fork, x.archived, x.stars as u32) => {}
+    _ => return AppError::not_found(),
+  }
+
+  let items = state.db.get_popular_items(repo, kind, qs).await?;
+  Ok(Json(items))
+}
+
+pub async fn api_get_referrers(
+  State(state): State<Arc<AppState>>,
+  Path((owner, repo)): Path<(String, String)>,
+  Query(qs): Query<PopularFilter>,
+) -> JsonRes<Vec<RepoPopularItem>> {
+  let repo = format!("{}/{}", owner, repo);
+  get_popular(&state, &repo, &PopularKind::Refs, &qs).await
+}
+
+pub async fn api_get_paths(
+  State(state): State<Arc<AppState>>,
+  Path((owner, repo)): Path<(String, String)>,
+  Query(qs): Query<PopularFilter>,
+) -> JsonRes<Vec<RepoPopularItem>> {
+  let repo = format!("{}/{}", owner, repo);
+  get_popular(&state, &repo, &PopularKind::Path, &qs).await
+}
+
+/// Latest known GitHub API rate limit (updated after each sync), `null` if not loaded yet
+pub async fn api_rate_limit(State(state): State<Arc<AppState>>) -> JsonRes<Option<RateLimit>> {
+  let rate = state.rate_limit.lock().unwrap().clone();
+  Ok(Json(rate))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct AppStatus {
+  version: String,
+  db_path: String,
+  last_update: Option<chrono::DateTime<chrono::Utc>>,
+  last_run: Option<RunStatus>,
+}
+
+pub async fn api_status(State(state): State<Arc<AppState>>) -> JsonRes<AppStatus> {
+  let status = AppStatus {
+    version: env!("CARGO_PKG_VERSION").to_string(),
+    db_path: state.db_path.clone(),
+    last_update: *state.last_update.lock().unwrap(),
+    last_run: state.last_run.lock().unwrap().clone(),
+  };
+
+  Ok(Json(status))
+}
+
+/// Effective config for troubleshooting (how filter rules were parsed), secrets never included
+pub async fn api_config(State(state): State<Arc<AppState>>) -> JsonRes<serde_json::Value> {
+  let config = serde_json::json!({
+    "version": env!("CARGO_PKG_VERSION"),
+    "cron": crate::CRON_EXPR,
+    "filter": &state.filter,
+    "include_private": state.include_private,
+    "visibility": &state.visibility,
+    "affiliation": &state.affiliation,
+    "org": &state.org,
+    "track_starred": state.track_starred,
+    "skip_fork_traffic": state.skip_fork_traffic,
+    "hide_inactive": state.hide_inactive,
+    "default_sort": &state.default_sort,
+    "default_direction": &state.default_direction,
+    "pinned": &state.pinned,
+    "columns": &state.columns,
+  });
+
+  Ok(Json(config))
+}
+
+pub async fn api_export(State(state): State<Arc<AppState>>) -> JsonRes<ExportData> {
+  let data = state.db.export_data().await?;
+  Ok(Json(data))
+}
+
+/// Daily stats as JSON Lines, streamed in chunks to not load whole table into memory.
+/// First line is header with schema version, checked on import.
+pub async fn api_export_jsonl(
+  State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+  let header = ExportHeader { version: state.db.get_version().await? };
+  let header = format!("{}\n", serde_json::to_string(&header)?);
+
+  let init: (Arc<AppState>, Option<(i64, String)>, bool) = (state, None, false);
+  let stream = stream::unfold(init, |(state, after, done)| async move {
+    if done {
+      return None;
+    }
+
+    let items = match state.db.export_stats_page(after.as_ref(), 1000).await {
+      Ok(items) if items.is_empty() => return None,
+      Ok(items) => items,
+      Err(e) => return Some((Err(e), (state, after, true))),
+    };
+
+    let mut buf = String::new();
+    for item in &items {
+      match serde_json::to_string(item) {
+        Ok(line) => buf.push_str(&line),
+        Err(e) => return Some((Err(e.into()), (state, after, true))),
+      }
+      buf.push('\n');
+    }
+
+    let last = items.last().map(|x| (x.stats.repo_id, x.stats.date.clone()));
+    Some((Ok::<_, anyhow::Error>(buf), (state, last, false)))
+  });
+
+  let stream = stream::once(async move { Ok(header) }).chain(stream);
+  Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], Body::from_stream(stream)))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ImportResult {
+  repos: usize,
+  stats: usize,
+  referrers: usize,
+  paths: usize,
+}
+
+fn bad_request(msg: String) -> AppError {
+  anyhow::anyhow!(StatusCode::BAD_REQUEST).context(msg).into()
+}
+
+/// Restore snapshot from `/export` (JSON) or `/export.jsonl` (JSON Lines, by content type).
+/// Whole payload parsed & validated before writing, so broken file does not touch database.
+pub async fn api_import(
+  State(state): State<Arc<AppState>>,
+  headers: HeaderMap,
+  body: Bytes,
+) -> JsonRes<ImportResult> {
+  let ctype = headers.get(header::CONTENT_TYPE).and_then(|x| x.to_str().ok()).unwrap_or_default();
+
+  if ctype.contains("ndjson") || ctype.contains("jsonl") {
+    let text = std::str::from_utf8(&body).map_err(|e| bad_request(e.to_string()))?;
+    let mut lines = text.lines().enumerate().filter(|(_, x)| !x.trim().is_empty());
+
+    let header: ExportHeader = match lines.next() {
+      Some((idx, line)) => serde_json::from_str(line)
+        .map_err(|e| bad_request(format!("line {}: version header expected: {}", idx + 1, e)))?,
+      None => return Err(bad_request("empty payload".to_string())),
+    };
+    header.validate(state.db.get_version().await?).map_err(|e| bad_request(e.to_string()))?;
+
+    let mut items: Vec<ExportStatsLine> = Vec::new();
+    for (idx, line) in lines {
+      let item = serde_json::from_str(line);
+      items.push(item.map_err(|e| bad_request(format!("line {}: {}", idx + 1, e)))?);
+    }
+
+    state.db.import_stats(&items).await.map_err(|e| bad_request(e.to_string()))?;
+    tracing::info!("imported {} stats lines", items.len());
+    return Ok(Json(ImportResult { repos: 0, stats: items.len(), referrers: 0, paths: 0 }));
+  }
+
+  let data: ExportData = serde_json::from_slice(&body).map_err(|e| bad_request(e.to_string()))?;
+  data.validate(state.db.get_version().await?).map_err(|e| bad_request(e.to_string()))?;
+  state.db.import_data(&data).await?;
+
+  let res = ImportResult {
+    repos: data.repos.len(),
+    stats: data.stats.len(),
+    referrers: data.referrers.len(),
+    paths: data.paths.len(),
+  };
+
+  tracing::info!("imported snapshot: {:?}", res);
+  Ok(Json(res))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct MetricsQuery {
+  history: Option<i32>,
+}
+
+type MetricGetter<T> = fn(&T) -> i32;
+
+/// Current totals of repos as OpenMetrics gauges, or with `history=N` – daily samples of last
+/// N days with explicit timestamps (for backfill with pushgateway / VictoriaMetrics import)
+pub async fn api_metrics(
+  State(state): State<Arc<AppState>>,
+  Query(qs): Query<MetricsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+  let repos = state.get_repos_filtered(&RepoFilter::default()).await?;
+  let mut out = String::new();
+
+  match qs.history.filter(|x| *x > 0) {
+    Some(days) => {
+      let mut items: Vec<(String, Vec<RepoMetrics>)> = vec![];
+      for repo in &repos {
+        items.push((repo.name.clone(), state.db.get_metrics(&repo.name, days).await?));
+      }
+
+      let metrics: [(&str, &str, MetricGetter<RepoMetrics>); 4] = [
+        ("ghstats_views", "Daily views", |x| x.views_count),
+        ("ghstats_views_uniques", "Daily unique visitors", |x| x.views_uniques),
+        ("ghstats_clones", "Daily clones", |x| x.clones_count),
+        ("ghstats_clones_uniques", "Daily unique cloners", |x| x.clones_uniques),
+      ];
+
+      for (name, help, getter) in metrics {
+        out.push_str(&format!("# TYPE {name} gauge\n# HELP {name} {help}\n"));
+        for (repo, rows) in &items {
+          for x in rows {
+            let ts = chrono::DateTime::parse_from_rfc3339(&x.date)?.timestamp();
+            out.push_str(&format!("{name}{{repo=\"{repo}\"}} {} {ts}\n", getter(x)));
+          }
+        }
+      }
+    }
+    None => {
+      let metrics: [(&str, &str, MetricGetter<RepoTotals>); 8] = [
+        ("ghstats_stars", "Stars count", |x| x.stars),
+        ("ghstats_forks", "Forks count", |x| x.forks),
+        ("ghstats_watchers", "Watchers count", |x| x.watchers),
+        ("ghstats_issues_open", "Open issues count", |x| x.issues_open),
+        ("ghstats_views", "Total views", |x| x.views_count),
+        ("ghstats_views_uniques", "Total unique visitors", |x| x.views_uniques),
+        ("ghstats_clones", "Total clones", |x| x.clones_count),
+        ("ghstats_clones_uniques", "Total unique cloners", |x| x.clones_uniques),
+      ];
+
+      for (name, help, getter) in metrics {
+        out.push_str(&format!("# TYPE {name} gauge\n# HELP {name} {help}\n"));
+        for repo in &repos {
+          out.push_str(&format!("{name}{{repo=\"{}\"}} {}\n", repo.name, getter(repo)));
+        }
+      }
+    }
+  }
+
+  out.push_str("# EOF\n");
+  let ctype = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+  Ok(([(header::CONTENT_TYPE, ctype)], out))
+}
+
+pub async fn api_openapi() -> JsonRes<serde_json::Value> {
+  let mut spec: serde_json::Value =
+    serde_json::from_str(include_str!("../../assets/openapi.json"))?;
+  spec["info"]["version"] = env!("CARGO_PKG_VERSION").into();
+
+  // keep query params in sync with enums
+  let sorts = vec![
+    RepoSort::Name,
+    RepoSort::Stars,
+    RepoSort::Forks,
+    RepoSort::Watchers,
+    RepoSort::Issues,
+    RepoSort::Prs,
+    RepoSort::Clones,
+    RepoSort::Views,
+    RepoSort::Pushed,
+    RepoSort::StarsNew,
+    RepoSort::ClonesUniques,
+    RepoSort::ViewsUniques,
+  ];
+  let sorts = sorts.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+  let directions = [Direction::Asc, Direction::Desc];
+  let directions = directions.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+  let params = &mut spec["paths"]["/api/repos"]["get"]["parameters"];
+  params[0]["schema"]["enum"] = sorts.into();
+  params[1]["schema"]["enum"] = directions.clone().into();
+
+  let popular_sorts = [PopularSort::Name, PopularSort::Count, PopularSort::Uniques];
+  let popular_sorts = popular_sorts.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+  for path in ["/api/repos/{owner}/{repo}/referrers", "/api/repos/{owner}/{repo}/paths"] {
+    let params = &mut spec["paths"][path]["get"]["parameters"];
+    params[2]["schema"]["enum"] = popular_sorts.clone().into();
+    params[3]["schema"]["enum"] = directions.clone().into();
+  }
+
+  Ok(Json(spec))
+}
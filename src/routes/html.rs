@@ -1,13 +1,19 @@
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
 use maud::{html, Markup, PreEscaped};
 use thousands::Separable;
 
 use crate::db_client::{
-  DbClient, Direction, PopularFilter, PopularKind, PopularSort, RepoFilter, RepoSort, RepoTotals,
+  DbClient, Direction, MetricsFilter, PopularFilter, PopularKind, PopularSort, RepoFilter,
+  RepoMetrics, RepoSort, RepoStars, RepoTotals, RepoWeekday,
+};
+use crate::helpers::{
+  format_ago, format_compact, format_size, parse_periods, pin_first, render_badge, truncate_middle,
+  url_encode,
 };
-use crate::helpers::truncate_middle;
 use crate::types::{AppError, HtmlRes};
 use crate::AppState;
 
@@ -18,6 +24,32 @@ struct TablePopularItem {
   count: i64,
 }
 
+/// Prefix absolute path with `GHS_BASE_PATH` (when served behind reverse proxy)
+fn url(path: &str) -> String {
+  static BASE_PATH: OnceLock<String> = OnceLock::new();
+  let base_path = BASE_PATH.get_or_init(|| {
+    let base_path = std::env::var("GHS_BASE_PATH").unwrap_or_default();
+    base_path.trim().trim_end_matches('/').to_string()
+  });
+
+  format!("{}{}", base_path, path)
+}
+
+/// Render number with thousands separators or compact (`GHS_COMPACT_NUMBERS`), exact in title
+fn num(value: impl Into<i64>) -> Markup {
+  static COMPACT: OnceLock<bool> = OnceLock::new();
+  let compact = COMPACT.get_or_init(|| {
+    let val = std::env::var("GHS_COMPACT_NUMBERS").unwrap_or_default().to_lowercase();
+    val == "true" || val == "1"
+  });
+
+  let value: i64 = value.into();
+  match compact {
+    true => html!(span title=(value.separate_with_commas()) { (format_compact(value)) }),
+    false => html!((value.separate_with_commas())),
+  }
+}
+
 fn get_hx_target(req: &Request) -> Option<&str> {
   crate::helpers::get_header(req, "hx-target")
 }
@@ -31,13 +63,104 @@ fn maybe_url(item: &(String, Option<String>)) -> Markup {
   }
 }
 
-fn get_custom_links() -> Vec<(String, String)> {
+/// Render "uniques / total" as on repo page, uniques dimmed to keep total readable
+fn uniques_total(uniques: i32, total: i32) -> Markup {
+  html!(span class="secondary" title="uniques" { (num(uniques)) } " / " (num(total)))
+}
+
+fn sparkline(values: Option<&Vec<i32>>) -> Markup {
+  let values = match values {
+    Some(x) if !x.is_empty() => x,
+    _ => {
+      return html!(span class="sparkline secondary" title="Stars history not synced yet" { "–" })
+    }
+  };
+
+  let (w, h) = (80.0, 20.0);
+  let min = *values.iter().min().unwrap_or(&0) as f64;
+  let max = *values.iter().max().unwrap_or(&0) as f64;
+  let step = w / (values.len().max(2) - 1) as f64;
+
+  let mut points = values
+    .iter()
+    .enumerate()
+    .map(|(idx, x)| {
+      let y = match max > min {
+        true => h - 1.0 - (*x as f64 - min) / (max - min) * (h - 2.0),
+        false => h / 2.0,
+      };
+      format!("{:.1},{:.1}", idx as f64 * step, y)
+    })
+    .collect::<Vec<_>>();
+
+  // single point drawn as flat line
+  if points.len() == 1 {
+    points.push(format!("{:.1},{:.1}", w, h / 2.0));
+  }
+
+  let title = format!("{} → {}", values[0], values[values.len() - 1]);
+  html!(
+    svg class="sparkline" width=(w) height=(h) viewBox=(format!("0 0 {w} {h}")) {
+      title { (title) }
+      polyline points=(points.join(" ")) fill="none" stroke="currentColor" stroke-width="1.5" {}
+    }
+  )
+}
+
+fn weekday_heatmap(items: &[RepoWeekday]) -> Markup {
+  const NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+  // average per day, so weekdays with more collected days do not dominate
+  let avgs = items
+    .iter()
+    .map(|x| match x.days {
+      0 => 0.0,
+      days => x.views_count as f64 / days as f64,
+    })
+    .collect::<Vec<_>>();
+
+  let max = avgs.iter().cloned().fold(0.0, f64::max);
+
+  html!(
+    div class="heatmap" {
+      // week starts on Monday
+      @for idx in [1, 2, 3, 4, 5, 6, 0] {
+        @let pct = match max > 0.0 {
+          true => (avgs[idx] / max * 100.0).round() as i32,
+          false => 0,
+        };
+        div class="heatmap-cell" style=(format!("--ghs-heat: {}%", pct))
+          title=(format!("{}: {:.1} views / day ({} days)", NAMES[idx], avgs[idx], items[idx].days)) {
+          small { (NAMES[idx]) }
+          small class="secondary" { (format!("{:.0}", avgs[idx])) }
+        }
+      }
+    }
+  )
+}
+
+fn delta_badge(value: i32) -> Markup {
+  match value {
+    0 => html!(),
+    x => html!(small class="ml-1" title="since yesterday" { (format!("{:+}", x)) " today" }),
+  }
+}
+
+struct CustomLink {
+  name: String,
+  url: String,
+  icon: Option<String>,
+  target: String,
+}
+
+/// Parse `GHS_CUSTOM_LINKS` as `name|url[|icon[|target]]` items separated by comma
+fn get_custom_links() -> Vec<CustomLink> {
   let links = std::env::var("GHS_CUSTOM_LINKS").unwrap_or_default();
-  let links: Vec<(String, String)> = links
+  let links: Vec<CustomLink> = links
     .split(",")
     .map(|x| {
-      let parts: Vec<&str> = x.split("|").collect();
-      if parts.len() != 2 {
+      let parts: Vec<&str> = x.split("|").map(|x| x.trim()).collect();
+      if parts.len() < 2 || parts.len() > 4 {
         return None;
       }
 
@@ -45,7 +168,9 @@ fn get_custom_links() -> Vec<(String, String)> {
         return None;
       }
 
-      Some((parts[0].to_string(), parts[1].to_string()))
+      let icon = parts.get(2).filter(|x| !x.is_empty()).map(|x| x.to_string());
+      let target = parts.get(3).filter(|x| !x.is_empty()).unwrap_or(&"_blank").to_string();
+      Some(CustomLink { name: parts[0].to_string(), url: parts[1].to_string(), icon, target })
     })
     .filter(|x| x.is_some())
     .map(|x| x.unwrap())
@@ -58,18 +183,34 @@ fn base(state: &Arc<AppState>, navs: Vec<(String, Option<String>)>, inner: Marku
   let (app_name, app_version) = (env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
 
   let last_release = state.last_release.lock().unwrap().clone();
-  let is_new_release = last_release != app_version;
+  let is_new_release = state.update_check && last_release != app_version;
+
+  let last_update = *state.last_update.lock().unwrap();
+  let last_update = last_update.map(|x| (x, format_ago((chrono::Utc::now() - x).num_seconds())));
 
+  let rate_limit = state.rate_limit.lock().unwrap().clone();
+  let rate_limit = rate_limit.map(|x| {
+    let reset = chrono::DateTime::from_timestamp(x.reset, 0).unwrap_or_default();
+    (x, reset.to_rfc3339())
+  });
+
+  let brand_name = state.brand_name.as_str();
   let title = match navs.len() {
-    0 => app_name,
-    _ => &format!("{} · {}", navs.last().unwrap().0, app_name),
+    0 => brand_name,
+    _ => &format!("{} · {}", navs.last().unwrap().0, brand_name),
   };
 
-  let favicon = include_str!("../../assets/favicon.svg")
-    .replace("\n", "")
-    .replace("\"", "%22")
-    .replace("#", "%23");
-  let favicon = format!("data:image/svg+xml,{}", favicon);
+  // custom favicon served by separate route, default one inlined
+  let (favicon_type, favicon) = match &state.favicon {
+    Some((content_type, _)) => (content_type.as_str(), url("/favicon")),
+    None => {
+      let favicon = include_str!("../../assets/favicon.svg")
+        .replace("\n", "")
+        .replace("\"", "%22")
+        .replace("#", "%23");
+      ("image/svg+xml", format!("data:image/svg+xml,{}", favicon))
+    }
+  };
 
   html!(
     html {
@@ -78,20 +219,23 @@ fn base(state: &Arc<AppState>, navs: Vec<(String, Option<String>)>, inner: Marku
         meta name="viewport" content="width=device-width, initial-scale=1" {}
         title { (title) }
 
-        link rel="icon" type="image/svg+xml" href=(PreEscaped(favicon)) {}
+        link rel="icon" type=(favicon_type) href=(PreEscaped(favicon)) {}
         link rel="stylesheet" href="https://unpkg.com/@picocss/pico@2.0" {}
         script src="https://unpkg.com/chart.js@4.4" {}
         script src="https://unpkg.com/luxon@3.5" {}
         script src="https://unpkg.com/chartjs-adapter-luxon@1.3" {}
         script src="https://unpkg.com/htmx.org@2.0" {}
         style { (PreEscaped(include_str!("../../assets/app.css"))) }
+        @if let Some(css) = &state.custom_css {
+          style { (PreEscaped(css)) }
+        }
       }
       body {
         main class="container-fluid pt-0 main-box" {
           div class="flex-row items-center gap-2 justify-between" {
             nav aria-label="breadcrumb" {
               ul {
-                li { a href="/" { "Repos" } }
+                li { a href=(url("/")) { "Repos" } }
                 @for item in navs {
                   li { (maybe_url(&item)) }
                 }
@@ -100,8 +244,21 @@ fn base(state: &Arc<AppState>, navs: Vec<(String, Option<String>)>, inner: Marku
 
             div class="flex-row items-center gap-2" {
               div class="flex-row items-center gap-4 pr-4" style="font-size: 18px;" {
-                @for (name, url) in &get_custom_links() {
-                  a href=(url) target="_blank" { (name) }
+                @for link in &get_custom_links() {
+                  a href=(link.url) target=(link.target) {
+                    @if let Some(icon) = &link.icon { (icon) " " }
+                    (link.name)
+                  }
+                }
+              }
+
+              @if let Some((date, ago)) = &last_update {
+                small class="secondary" title=(date.to_rfc3339()) { "updated " (ago) }
+              }
+
+              @if let Some((rate, reset)) = &rate_limit {
+                small class="secondary" title=(format!("GitHub API rate limit, resets at {}", reset)) {
+                  "api " (rate.remaining.separate_with_commas()) " / " (rate.limit.separate_with_commas())
                 }
               }
 
@@ -131,6 +288,7 @@ fn base(state: &Arc<AppState>, navs: Vec<(String, Option<String>)>, inner: Marku
 async fn popular_table(
   db: &DbClient,
   repo: &str,
+  page_url: &str,
   kind: &PopularKind,
   qs: &PopularFilter,
 ) -> HtmlRes {
@@ -149,7 +307,13 @@ async fn popular_table(
           name = "/".to_string();
         }
 
-        let item = (name, Some(format!("https://github.com{}", x.name)));
+        // github link contains repo name
+        let link = match qs.anonymize {
+          true => None,
+          false => Some(format!("https://github.com{}", x.name)),
+        };
+
+        let item = (name, link);
         TablePopularItem { item, uniques: x.uniques, count: x.count }
       })
       .collect(),
@@ -167,17 +331,18 @@ async fn popular_table(
 
   let cols: Vec<(&str, Box<dyn Fn(&TablePopularItem) -> Markup>, PopularSort)> = vec![
     (name, Box::new(|x| maybe_url(&x.item)), PopularSort::Name),
-    ("Views", Box::new(|x| html!((x.count.separate_with_commas()))), PopularSort::Count),
-    ("Unique", Box::new(|x| html!((x.uniques.separate_with_commas()))), PopularSort::Uniques),
+    ("Views", Box::new(|x| num(x.count)), PopularSort::Count),
+    ("Unique", Box::new(|x| num(x.uniques)), PopularSort::Uniques),
   ];
 
-  fn filter_url(repo: &str, qs: &PopularFilter, col: &PopularSort) -> String {
+  fn filter_url(page_url: &str, qs: &PopularFilter, col: &PopularSort) -> String {
     let dir = match qs.sort == *col && qs.direction == Direction::Desc {
       true => "asc",
       false => "desc",
     };
 
-    format!("/{}?sort={}&direction={}&period={}", repo, col, dir, qs.period)
+    let root = if qs.exclude_root { "&exclude_root=1" } else { "" };
+    format!("{}?sort={}&direction={}&period={}{}", page_url, col, dir, qs.period, root)
   }
 
   let html = html!(
@@ -188,8 +353,9 @@ async fn popular_table(
             @for (idx, col) in cols.iter().enumerate() {
               th scope="col" .cursor-pointer .select-none .text-right[idx > 0]
                 hx-trigger="click"
-                hx-get=(filter_url(repo, qs, &col.2))
+                hx-get=(filter_url(page_url, qs, &col.2))
                 hx-target=(format!("#{}", html_id))
+                hx-indicator=(format!("#{}", html_id))
                 hx-swap="outerHTML"
               {
                 (col.0)
@@ -225,98 +391,250 @@ async fn popular_table(
   Ok(html)
 }
 
-async fn repo_popular_tables(db: &DbClient, repo: &str, filter: &PopularFilter) -> HtmlRes {
+async fn repo_popular_tables(
+  db: &DbClient,
+  repo: &str,
+  page_url: &str,
+  filter: &PopularFilter,
+) -> HtmlRes {
   let html = html!(
     div id="popular_tables" class="grid" {
-      (popular_table(db, repo, &PopularKind::Refs, filter).await?)
-      (popular_table(db, repo, &PopularKind::Path, filter).await?)
+      (popular_table(db, repo, page_url, &PopularKind::Refs, filter).await?)
+      (popular_table(db, repo, page_url, &PopularKind::Path, filter).await?)
     }
   );
 
   return Ok(html);
 }
 
+/// Styled 404 page for unknown (or filtered out) repos, status code kept
+fn not_found_page(state: &Arc<AppState>, repo: &str) -> Response {
+  let html = html!(
+    article class="text-center" {
+      h3 { "Repo not found" }
+      p { "Repo " code { (repo) } " is not tracked or was renamed / removed." }
+      a href=(url("/")) { "Back to all repos" }
+    }
+  );
+
+  let html = base(state, vec![("Not found".to_string(), None)], html);
+  (StatusCode::NOT_FOUND, html).into_response()
+}
+
 pub async fn repo_page(
   State(state): State<Arc<AppState>>,
-  Path((owner, repo)): Path<(String, String)>,
+  Path((owner, name)): Path<(String, String)>,
   req: Request,
-) -> HtmlRes {
-  let repo = format!("{}/{}", owner, repo);
+) -> Result<Response, AppError> {
   let mut qs: Query<PopularFilter> = Query::try_from_uri(req.uri())?;
   let db = &state.db;
 
-  let periods = vec![
-    (7, "Last 7 days"),
-    (14, "Last 14 days"),
-    (30, "Last 30 days"),
-    (90, "Last 90 days"),
-    (-1, "All time"),
-  ];
+  // anonymized private repo page served by alias (`/-/private-repo-N`), so url has no real name
+  let (repo, alias) = match owner.as_str() {
+    "-" => {
+      // only listed repos resolved, not found page shows alias, so hidden names are not exposed
+      let totals = match db.get_repo_by_alias(&name).await? {
+        Some(repo) => db.get_repo_totals(&repo).await?,
+        None => None,
+      };
 
-  qs.period = match periods.iter().all(|x| x.0 != qs.period) {
+      match totals {
+        Some(x) if state.filter.is_included(&x.name, x.fork, x.archived, x.stars as u32) => {
+          (x.name, Some(name))
+        }
+        _ => return Ok(not_found_page(&state, &name)),
+      }
+    }
+    _ => (format!("{}/{}", owner, name), None),
+  };
+  let page_url = match &alias {
+    Some(alias) => url(&format!("/-/{}", alias)),
+    None => url(&format!("/{}", repo)),
+  };
+  let redirect_to_alias = qs.anonymize && alias.is_none();
+  qs.anonymize = alias.is_some();
+
+  static PERIODS: OnceLock<Vec<(i32, String)>> = OnceLock::new();
+  let periods =
+    PERIODS.get_or_init(|| parse_periods(&std::env::var("GHS_PERIODS").unwrap_or_default()));
+
+  // last 7 days by default, or shortest configured period if 7 not in list
+  let default_period = match periods.iter().any(|x| x.0 == 7) {
     true => 7,
+    false => periods[0].0,
+  };
+
+  qs.period = match periods.iter().all(|x| x.0 != qs.period) {
+    true => default_period,
     false => qs.period,
   };
 
-  match get_hx_target(&req) {
-    Some("refs_table") => return Ok(popular_table(db, &repo, &PopularKind::Refs, &qs).await?),
-    Some("path_table") => return Ok(popular_table(db, &repo, &PopularKind::Path, &qs).await?),
-    Some("popular_tables") => return Ok(repo_popular_tables(&db, &repo, &qs).await?),
-    _ => {}
+  let mut ms: Query<MetricsFilter> = Query::try_from_uri(req.uri())?;
+  ms.range = match periods.iter().all(|x| x.0 != ms.range) {
+    true => -1,
+    false => ms.range,
+  };
+
+  let html = match get_hx_target(&req) {
+    Some("refs_table") => Some(popular_table(db, &repo, &page_url, &PopularKind::Refs, &qs).await?),
+    Some("path_table") => Some(popular_table(db, &repo, &page_url, &PopularKind::Path, &qs).await?),
+    Some("popular_tables") => Some(repo_popular_tables(db, &repo, &page_url, &qs).await?),
+    _ => None,
+  };
+
+  if let Some(html) = html {
+    return Ok(html.into_response());
   }
 
+  let shown_name = alias.clone().unwrap_or(repo.clone());
   let totals = match db.get_repo_totals(&repo).await? {
     Some(x) => x,
-    None => return AppError::not_found(),
+    None if alias.is_some() => return Ok(not_found_page(&state, &shown_name)),
+    None => match db.get_renamed_repo(&repo).await? {
+      Some(name) => {
+        let headers = [(header::LOCATION, url(&format!("/{}", name)))];
+        return Ok((StatusCode::MOVED_PERMANENTLY, headers).into_response());
+      }
+      None => return Ok(not_found_page(&state, &repo)),
+    },
   };
 
-  if !state.filter.is_included(&totals.name, totals.fork, totals.archived) {
-    return AppError::not_found();
+  if !state.filter.is_included(&totals.name, totals.fork, totals.archived, totals.stars as u32) {
+    return Ok(not_found_page(&state, &shown_name));
+  }
+
+  // public repos shown as is, private ones opened with `?anonymize=1` moved to alias url
+  if redirect_to_alias && totals.private {
+    if let Some(alias) = db.get_private_aliases().await?.remove(&totals.id) {
+      let headers = [(header::LOCATION, url(&format!("/-/{}", alias)))];
+      return Ok((StatusCode::SEE_OTHER, headers).into_response());
+    }
   }
 
-  let metrics = db.get_metrics(&repo).await?;
-  let stars = db.get_stars(&repo).await?;
+  let metrics = db.get_metrics(&repo, ms.range).await?;
+  let stars = db.get_stars(&repo, ms.range).await?;
+  let forks = db.get_forks(&repo, ms.range).await?;
+  let issues = db.get_issues_history(&repo, ms.range).await?;
+  let delta = db.get_daily_delta(&repo).await?.unwrap_or_default();
+  let tracking = db.get_tracking_summary(&repo).await?;
+  let stars_30d = db.get_new_stars(&repo, 30).await?;
+  // same window as GitHub Insights traffic, not needed if totals already use it
+  let github_window = match db.totals_window() {
+    14 => None,
+    _ => Some(db.get_window_metrics(&repo, 14).await?),
+  };
+  let topics = db.get_repo_topics(&repo).await?;
+  let weekdays = db.get_weekday_distribution(&repo).await?;
+  let totals_prefix = match db.totals_window() {
+    x if x > 0 => format!("{}d", x),
+    _ => "Total".to_string(),
+  };
 
   let html = html!(
+    form method="get" action=(page_url) class="flex-row justify-end mb-0" {
+      input type="hidden" name="period" value=(qs.period) {}
+      @if qs.exclude_root {
+        input type="hidden" name="exclude_root" value="1" {}
+      }
+      select name="range" onchange="this.form.submit()" class="mb-0" style="width: auto;" {
+        @for (days, title) in periods {
+          option value=(days) selected[*days == ms.range] { "Charts: " (title) }
+        }
+      }
+    }
+
     div class="grid" style="grid-template-columns: 1fr 2fr;" {
       div class="grid" style="grid-template-rows: 2fr 1fr; grid-template-columns: 1fr;" {
         article class="mb-0" {
           hgroup class="flex-row flex-col gap-2" {
-            h3 {
-              a href=(format!("https://github.com/{}", repo)) class="contrast" { (totals.name) }
+            @if let Some(alias) = &alias {
+              h3 { (alias) }
+              p class="secondary" { "Private repo" }
+            } @else {
+              h3 {
+                a href=(format!("https://github.com/{}", repo)) class="contrast" { (totals.name) }
+              }
+              p { (totals.description.unwrap_or("".to_string())) }
+            }
+          }
+          // topics often tell what project is about
+          @if !topics.is_empty() && alias.is_none() {
+            div class="flex-row flex-wrap gap-2 mb-2" {
+              @for topic in &topics {
+                a href=(url(&format!("/?topic={}", url_encode(topic)))) class="tag" { (topic) }
+              }
+            }
+          }
+          small class="flex-row gap-4" {
+            span { "Size: " (format_size(totals.size as i64)) }
+            @if totals.downloads > 0 {
+              span { "Downloads: " (totals.downloads.separate_with_commas()) }
+            }
+            @if let Some(branch) = &totals.default_branch {
+              span { "Branch: " code { (branch) } }
+            }
+            @if let Some(first_seen) = &totals.first_seen {
+              span title="Date ghstats started tracking repo" {
+                "Tracked since: " (first_seen.split("T").next().unwrap_or(""))
+              }
             }
-            p { (totals.description.unwrap_or("".to_string())) }
           }
         }
 
         div class="grid" {
           article class="flex-col" {
-            h6 class="mb-0" { "Total Clones" }
+            h6 class="mb-0" { (totals_prefix) " Clones" }
             h4 class="mb-0 grow flex-row items-center" {
               (totals.clones_uniques.separate_with_commas())
               " / "
               (totals.clones_count.separate_with_commas())
+              (delta_badge(delta.clones_count))
             }
           }
           article class="flex-col" {
-            h6 class="mb-0" { "Total Views" }
+            h6 class="mb-0" { (totals_prefix) " Views" }
             h4 class="mb-0 grow flex-row items-center" {
               (totals.views_uniques.separate_with_commas())
               " / "
               (totals.views_count.separate_with_commas())
+              (delta_badge(delta.views_count))
+            }
+          }
+          @if let Some(w) = &github_window {
+            article class="flex-col" title="Same window as GitHub Insights traffic (uniques summed by day, so can be higher)" {
+              h6 class="mb-0" { "Last 14 days (matches GitHub)" }
+              small { "Clones: " (w.clones_uniques.separate_with_commas()) " / " (w.clones_count.separate_with_commas()) }
+              small { "Views: " (w.views_uniques.separate_with_commas()) " / " (w.views_count.separate_with_commas()) }
+            }
+          }
+          @if let Some(tracking) = &tracking {
+            article class="flex-col" {
+              h6 class="mb-0" { "Tracked" }
+              h4 class="mb-0 grow flex-row items-center" title=(format!("since {}", tracking.since)) {
+                (tracking.days.separate_with_commas()) " days"
+              }
+              small {
+                "since " (tracking.since.split("T").next().unwrap_or(""))
+                @if tracking.stars != 0 { ", " (format!("{:+}", tracking.stars)) " stars" }
+              }
+              small { "Clones: " (tracking.clones_count.separate_with_commas()) }
+              small { "Views: " (tracking.views_count.separate_with_commas()) }
             }
           }
         }
       }
 
       article class="flex-col" {
-        h6 { "Stars" }
+        h6 class="flex-row justify-between" {
+          span { "Stars" (delta_badge(delta.stars)) }
+          small class="secondary" { (format!("{:+}", stars_30d)) " in 30 days" }
+        }
         div class="grow" { canvas id="chart_stars" {} }
       }
     }
 
     div class="grid" {
-      @for (title, canvas_id) in vec![("Clones", "chart_clones"), ("Views", "chart_views")] {
+      @for (title, canvas_id) in [("Clones", "chart_clones"), ("Views", "chart_views"), ("Forks", "chart_forks"), ("Open issues", "chart_issues")] {
         article {
           h6 { (title) }
           canvas id=(canvas_id) {}
@@ -324,82 +642,394 @@ pub async fn repo_page(
       }
     }
 
+    article {
+      h6 { "Views by weekday (avg per day)" }
+      (weekday_heatmap(&weekdays))
+    }
+
     script { (PreEscaped(include_str!("../../assets/app.js"))) }
     script {
       "const Metrics = "(PreEscaped(serde_json::to_string(&metrics)?))";"
+      "const Forks = "(PreEscaped(serde_json::to_string(&forks)?))";"
       "const Stars = "(PreEscaped(serde_json::to_string(&stars)?))";"
+      "const Issues = "(PreEscaped(serde_json::to_string(&issues)?))";"
       "renderMetrics('chart_clones', Metrics, 'clones_uniques', 'clones_count');"
       "renderMetrics('chart_views', Metrics, 'views_uniques', 'views_count');"
       "renderStars('chart_stars', Stars);"
+      "renderStars('chart_forks', Forks, 'forks');"
+      "renderStars('chart_issues', Issues, 'issues');"
     }
 
-    select name="period" hx-get=(format!("/{}", repo)) hx-target="#popular_tables" hx-swap="outerHTML" {
-      @for (days, title) in &periods {
-        option value=(days) selected[*days == qs.period] { (title) }
+    div class="flex-row items-center gap-4" {
+      select name="period" hx-get=(page_url) hx-target="#popular_tables" hx-indicator="#popular_tables" hx-swap="outerHTML" hx-include="#exclude_root" id="popular_period" {
+        @for (days, title) in periods {
+          option value=(days) selected[*days == qs.period] { (title) }
+        }
+      }
+      label class="flex-row items-center mb-0" style="white-space: nowrap;" {
+        input type="checkbox" id="exclude_root" name="exclude_root" value="1" checked[qs.exclude_root] hx-get=(page_url) hx-target="#popular_tables" hx-indicator="#popular_tables" hx-swap="outerHTML" hx-include="#popular_period" {}
+        "Hide repo root path"
       }
     }
 
-    (repo_popular_tables(db, &repo, &qs).await?)
+    (repo_popular_tables(db, &repo, &page_url, &qs).await?)
   );
 
-  Ok(base(&state, vec![(repo, None)], html))
+  Ok(base(&state, vec![(shown_name, None)], html).into_response())
+}
+
+pub async fn favicon(State(state): State<Arc<AppState>>) -> Result<Response, AppError> {
+  match &state.favicon {
+    Some((content_type, body)) => {
+      let headers = [
+        (header::CONTENT_TYPE, content_type.clone()),
+        (header::CACHE_CONTROL, "public, max-age=86400".to_string()),
+      ];
+      Ok((headers, body.clone()).into_response())
+    }
+    None => AppError::not_found(),
+  }
+}
+
+const BADGE_METRICS: [&str; 6] = ["stars", "forks", "views", "clones", "issues", "prs"];
+
+pub async fn repo_badge(
+  State(state): State<Arc<AppState>>,
+  Path((owner, repo, metric)): Path<(String, String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+  let repo = format!("{}/{}", owner, repo);
+  let metric = metric.trim_end_matches(".svg");
+  if !BADGE_METRICS.contains(&metric) {
+    return AppError::not_found();
+  }
+
+  let totals = match state.db.get_repo_totals(&repo).await? {
+    Some(x) if state.filter.is_included(&x.name, x.fork, x.archived, x.stars as u32) => Some(x),
+    _ => None,
+  };
+
+  let value = totals.map(|x| match metric {
+    "stars" => Some(x.stars),
+    "forks" => Some(x.forks),
+    "views" => Some(x.views_count),
+    "clones" => Some(x.clones_count),
+    "issues" => Some(x.issues_open),
+    "prs" => Some(x.prs),
+    _ => None,
+  });
+
+  // unknown repos rendered as badge too, so embeds do not break
+  let svg = match value.flatten() {
+    Some(x) => render_badge(metric, &x.separate_with_commas(), "#007ec6"),
+    None => render_badge(metric, "not found", "#9f9f9f"),
+  };
+
+  let headers =
+    [(header::CONTENT_TYPE, "image/svg+xml"), (header::CACHE_CONTROL, "public, max-age=300")];
+
+  Ok((headers, svg))
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct CompareFilter {
+  repos: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct CompareItem {
+  name: String,
+  metrics: Vec<RepoMetrics>,
+  stars: Vec<RepoStars>,
+}
+
+pub async fn compare_page(State(state): State<Arc<AppState>>, req: Request) -> HtmlRes {
+  const MAX_REPOS: usize = 5; // to keep charts readable
+
+  let qs: Query<CompareFilter> = Query::try_from_uri(req.uri())?;
+  let names = qs.repos.split(",").map(|x| x.trim()).filter(|x| !x.is_empty());
+  let names = names.take(MAX_REPOS).collect::<Vec<_>>();
+
+  let mut items: Vec<CompareItem> = Vec::new();
+  let mut missing: Vec<&str> = Vec::new();
+  for name in names {
+    let totals = match state.db.get_repo_totals(name).await? {
+      Some(x) if state.filter.is_included(&x.name, x.fork, x.archived, x.stars as u32) => x,
+      _ => {
+        missing.push(name);
+        continue;
+      }
+    };
+
+    let metrics = state.db.get_metrics(&totals.name, -1).await?;
+    let stars = state.db.get_stars(&totals.name, -1).await?;
+    items.push(CompareItem { name: totals.name, metrics, stars });
+  }
+
+  let charts = vec![
+    ("Stars", "chart_stars", "stars", "stars"),
+    ("Views", "chart_views", "metrics", "views_count"),
+    ("Clones", "chart_clones", "metrics", "clones_count"),
+  ];
+
+  let html = html!(
+    form method="get" action=(url("/compare")) class="mb-0" {
+      fieldset role="group" {
+        input type="text" name="repos" value=(qs.repos)
+          placeholder=(format!("owner/repo1,owner/repo2 (up to {} repos)", MAX_REPOS)) {}
+        input type="submit" value="Compare" {}
+      }
+    }
+
+    @for name in &missing {
+      article { "Repo " code { (name) } " not found" }
+    }
+
+    @if !items.is_empty() {
+      @for (title, canvas_id, _, _) in &charts {
+        article {
+          h6 { (title) }
+          canvas id=(canvas_id) {}
+        }
+      }
+
+      script { (PreEscaped(include_str!("../../assets/app.js"))) }
+      script {
+        "const Items = "(PreEscaped(serde_json::to_string(&items)?))";"
+        @for (_, canvas_id, src, col) in &charts {
+          (PreEscaped(format!("renderCompare('{canvas_id}', Items, '{src}', '{col}');")))
+        }
+      }
+    }
+  );
+
+  Ok(base(&state, vec![("Compare".to_string(), None)], html))
 }
 
 // https://docs.rs/axum/latest/axum/extract/index.html#common-extractors
 pub async fn index(State(state): State<Arc<AppState>>, req: Request) -> HtmlRes {
-  // let qs: Query<HashMap<String, String>> = Query::try_from_uri(req.uri())?;
-  let qs: Query<RepoFilter> = Query::try_from_uri(req.uri())?;
+  let qs = state.get_repo_filter(req.uri())?;
   let repos = state.get_repos_filtered(&qs).await?;
+  let repos_ids = repos.iter().map(|x| x.id).collect::<Vec<_>>();
+  let pages = qs.pages_count(repos.len());
+
+  // pinned repos on top of first page regardless of sort
+  let (repos, pinned_count) = pin_first(repos, &state.pinned, |x| x.name.as_str());
+  let pinned_count = match qs.page.unwrap_or(1) {
+    0 | 1 => pinned_count,
+    _ => 0,
+  };
+  let repos = qs.paginate(repos);
+
+  let deltas = match state.show_deltas {
+    true => state.db.get_daily_deltas().await?,
+    false => Default::default(),
+  };
+
+  let stars_title = format!("Stars {}d", qs.stars_days());
+  let stars_trend = match state.columns.iter().any(|x| x == "trend") {
+    true => state.db.get_recent_stars_all(qs.stars_days()).await?,
+    false => Default::default(),
+  };
+
+  let aliases = match qs.anonymize {
+    true => state.db.get_private_aliases().await?,
+    false => Default::default(),
+  };
+  let anon_qs = if qs.anonymize { "&anonymize=1" } else { "" };
 
-  let cols: Vec<(&str, Box<dyn Fn(&RepoTotals) -> Markup>, RepoSort)> = vec![
-    ("Name", Box::new(|x| html!(a href=(format!("/{}", x.name)) { (x.name) })), RepoSort::Name),
-    ("Issues", Box::new(|x| html!((x.issues.separate_with_commas()))), RepoSort::Issues),
-    ("PRs", Box::new(|x| html!((x.prs.separate_with_commas()))), RepoSort::Prs),
-    ("Forks", Box::new(|x| html!((x.forks.separate_with_commas()))), RepoSort::Forks),
-    ("Clones", Box::new(|x| html!((x.clones_count.separate_with_commas()))), RepoSort::Clones),
-    ("Stars", Box::new(|x| html!((x.stars.separate_with_commas()))), RepoSort::Stars),
-    ("Views", Box::new(|x| html!((x.views_count.separate_with_commas()))), RepoSort::Views),
+  type Col<'a> = (&'a str, Box<dyn Fn(&RepoTotals) -> Markup + Send + Sync + 'a>, Option<RepoSort>);
+  let mut all_cols: Vec<Col> = vec![
+    (
+      "Name",
+      Box::new(|x| match x.hidden {
+        true if aliases.contains_key(&x.id) => {
+          html!(span title="Private repo" { (aliases[&x.id]) " (hidden)" })
+        }
+        // page linked by alias, so url does not contain repo name
+        false if aliases.contains_key(&x.id) => html!(
+          a href=(url(&format!("/-/{}", aliases[&x.id]))) title="Private repo" { (aliases[&x.id]) }
+        ),
+        true => {
+          html!(span title="Repo is no longer accessible with token" { (x.name) " (hidden)" })
+        }
+        false => html!(
+          a href=(url(&format!("/{}", x.name))) { (x.name) }
+          @if x.watched {
+            small class="secondary ml-1" title="Starred repo, traffic not available" { "watched" }
+          }
+        ),
+      }),
+      Some(RepoSort::Name),
+    ),
+    ("Issues", Box::new(|x| num(x.issues_open)), Some(RepoSort::Issues)),
+    ("PRs", Box::new(|x| num(x.prs)), Some(RepoSort::Prs)),
+    ("Forks", Box::new(|x| num(x.forks)), Some(RepoSort::Forks)),
+    ("Watchers", Box::new(|x| num(x.watchers)), Some(RepoSort::Watchers)),
+    (
+      "Clones",
+      Box::new(|x| uniques_total(x.clones_uniques, x.clones_count)),
+      Some(RepoSort::Clones),
+    ),
+    ("Stars", Box::new(|x| num(x.stars)), Some(RepoSort::Stars)),
+    (stars_title.as_str(), Box::new(|x| num(x.stars_new)), Some(RepoSort::StarsNew)),
+    ("Views", Box::new(|x| uniques_total(x.views_uniques, x.views_count)), Some(RepoSort::Views)),
+    (
+      "Pushed",
+      Box::new(|x| html!((x.pushed_at.as_deref().unwrap_or("").split("T").next().unwrap_or("")))),
+      Some(RepoSort::Pushed),
+    ),
+    ("Trend", Box::new(|x| sparkline(stars_trend.get(&x.id))), None),
   ];
 
+  // columns selected with GHS_COLUMNS, keyed by sort name
+  let mut cols: Vec<Col> = vec![];
+  for key in &state.columns {
+    let pos = all_cols.iter().position(|x| match &x.2 {
+      Some(sort) => {
+        [Some(sort.clone()), sort.uniques()].iter().flatten().any(|x| x.to_string() == *key)
+      }
+      None => key == "trend",
+    });
+
+    if let Some(pos) = pos {
+      cols.push(all_cols.remove(pos));
+    }
+  }
+
+  // optional column with changes since yesterday (not sortable)
+  if state.show_deltas {
+    cols.push((
+      "Today",
+      Box::new(|x| {
+        let d = deltas.get(&x.id).cloned().unwrap_or_default();
+        let title =
+          format!("views {:+}, clones {:+}, stars {:+}", d.views_count, d.clones_count, d.stars);
+        html!(span title=(title) { (format!("{:+}", d.views_count)) })
+      }),
+      None,
+    ));
+  }
+
   fn filter_url(qs: &RepoFilter, col: &RepoSort) -> String {
     let dir = match qs.sort == *col && qs.direction == Direction::Desc {
       true => "asc",
       false => "desc",
     };
 
-    format!("/?sort={}&direction={}", col, dir)
+    url(&format!("/?sort={}&direction={}{}", col, dir, extra_qs(qs)))
+  }
+
+  fn page_url(qs: &RepoFilter, page: usize) -> String {
+    url(&format!("/?sort={}&direction={}&page={}{}", qs.sort, qs.direction, page, extra_qs(qs)))
+  }
+
+  // params to carry across sort / page links
+  fn extra_qs(qs: &RepoFilter) -> String {
+    let mut rs = String::new();
+    if let Some(per_page) = qs.per_page {
+      rs.push_str(&format!("&per_page={}", per_page));
+    }
+    if let Some(include_private) = qs.include_private {
+      rs.push_str(&format!("&include_private={}", include_private));
+    }
+    if let Some(active) = qs.active {
+      rs.push_str(&format!("&active={}", active));
+    }
+    if qs.show_hidden {
+      rs.push_str("&show_hidden=1");
+    }
+    if let Some(stars_days) = qs.stars_days {
+      rs.push_str(&format!("&stars_days={}", stars_days));
+    }
+    if let Some(topic) = &qs.topic {
+      rs.push_str(&format!("&topic={}", url_encode(topic)));
+    }
+    if let Some(owner) = &qs.owner {
+      rs.push_str(&format!("&owner={}", url_encode(owner)));
+    }
+    if qs.anonymize {
+      rs.push_str("&anonymize=1");
+    }
+    rs
   }
 
+  let current_page = qs.page.unwrap_or(1).max(1) as usize;
+
   let html = html!(
       table id="repos_table" {
         thead {
           tr {
             @for col in &cols {
-              th scope="col" class="cursor-pointer select-none"
-                hx-trigger="click"
-                hx-get=(filter_url(&qs, &col.2))
-                hx-target="#repos_table"
-                hx-swap="outerHTML"
-                {
-                  (col.0)
-                  @if col.2 == qs.sort {
-                    span class="ml-0.5" {
-                      @if qs.direction == Direction::Asc { "↑" } @else { "↓" }
+              @if let Some(sort) = &col.2 {
+                th scope="col" class="cursor-pointer select-none"
+                  hx-trigger="click"
+                  hx-get=(filter_url(&qs, sort))
+                  hx-target="#repos_table"
+                  hx-indicator="#repos_table"
+                  hx-swap="outerHTML"
+                  {
+                    (col.0)
+                    @if *sort == qs.sort {
+                      span class="ml-0.5" {
+                        @if qs.direction == Direction::Asc { "↑" } @else { "↓" }
+                      }
+                    }
+                    // uniques shown in same column, so sorted by separate link in header
+                    @if let Some(uniques) = sort.uniques() {
+                      small class="secondary ml-1" title="Sort by uniques"
+                        hx-trigger="click consume"
+                        hx-get=(filter_url(&qs, &uniques))
+                        hx-target="#repos_table"
+                        hx-indicator="#repos_table"
+                        hx-swap="outerHTML"
+                        {
+                          "uniq"
+                          @if uniques == qs.sort {
+                            span class="ml-0.5" {
+                              @if qs.direction == Direction::Asc { "↑" } @else { "↓" }
+                            }
+                          }
+                        }
                     }
                   }
-                }
+              } @else {
+                th scope="col" { (col.0) }
+              }
             }
           }
         }
         tbody {
-          @for repo in &repos {
-            tr {
+          @for (idx, repo) in repos.iter().enumerate() {
+            tr class=[(idx + 1 == pinned_count && idx + 1 < repos.len()).then_some("pinned-last")] {
               @for col in &cols {
                 td { ((col.1)(&repo)) }
               }
             }
           }
         }
+        @if pages > 1 {
+          tfoot {
+            tr {
+              td colspan=(cols.len()) {
+                div class="flex-row items-center justify-center gap-4" {
+                  @for page in 1..=pages {
+                    @if page == current_page {
+                      span class="font-mono" { (page) }
+                    } @else {
+                      a href=(page_url(&qs, page)) class="font-mono"
+                        hx-get=(page_url(&qs, page))
+                        hx-target="#repos_table"
+                        hx-indicator="#repos_table"
+                        hx-swap="outerHTML"
+                      { (page) }
+                    }
+                  }
+                }
+              }
+            }
+          }
+        }
       }
   );
 
@@ -408,5 +1038,127 @@ pub async fn index(State(state): State<Arc<AppState>>, req: Request) -> HtmlRes
     _ => {}
   }
 
+  let metrics = state.db.get_aggregate_metrics(&repos_ids).await?;
+  let stars = state.db.get_aggregate_stars(&repos_ids).await?;
+
+  let html = html!(
+    div class="grid" {
+      @for (title, canvas_id) in [("Clones", "chart_clones"), ("Views", "chart_views"), ("Stars", "chart_stars")] {
+        article {
+          h6 { (title) }
+          canvas id=(canvas_id) {}
+        }
+      }
+    }
+
+    script { (PreEscaped(include_str!("../../assets/app.js"))) }
+    script {
+      "const Metrics = "(PreEscaped(serde_json::to_string(&metrics)?))";"
+      "const Stars = "(PreEscaped(serde_json::to_string(&stars)?))";"
+      "renderMetrics('chart_clones', Metrics, 'clones_uniques', 'clones_count');"
+      "renderMetrics('chart_views', Metrics, 'views_uniques', 'views_count');"
+      "renderStars('chart_stars', Stars);"
+    }
+
+    div class="flex-row justify-end gap-4" {
+      @if let Some(topic) = &qs.topic {
+        a href=(url("/")) title="Clear topic filter" { "Topic: " (topic) " ×" }
+      }
+
+      @if qs.active == Some(true) {
+        a href=(url(&format!("/?active=false{}", anon_qs))) { "Show inactive repos" }
+      } @else {
+        a href=(url(&format!("/?active=true{}", anon_qs))) { "Hide inactive repos" }
+      }
+
+      @if state.include_private {
+        @if qs.include_private == Some(false) {
+          a href=(url(&format!("/?include_private=true{}", anon_qs))) { "Show private repos" }
+        } @else {
+          a href=(url(&format!("/?include_private=false{}", anon_qs))) { "Hide private repos" }
+        }
+
+        // for screen-sharing, private repo names replaced with aliases
+        @if qs.anonymize {
+          a href=(url("/")) { "Show private names" }
+        } @else {
+          a href=(url("/?anonymize=1")) { "Anonymize private repos" }
+        }
+      }
+    }
+
+    (html)
+  );
+
   Ok(base(&state, vec![], html))
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db_client::TestDb;
+  use crate::gh_client::Repo;
+
+  async fn test_state(db: &TestDb) -> Arc<AppState> {
+    // pages read config from env, so state made same way as in main with test db
+    std::env::set_var("DB_PATH", db.path.to_str().unwrap());
+    std::env::set_var("GHS_ALLOW_ANONYMOUS", "true");
+    Arc::new(AppState::new().await.unwrap())
+  }
+
+  #[tokio::test]
+  async fn test_render_pages() {
+    let db = TestDb::new().await;
+    let repo = Repo { id: 1, full_name: "foo/bar".to_string(), ..Default::default() };
+    let date = chrono::Utc::now().format("%Y-%m-%dT00:00:00Z").to_string();
+    db.seed_repo(&repo, &date).await;
+    let state = test_state(&db).await;
+
+    let req = Request::builder().uri("/").body(axum::body::Body::empty()).unwrap();
+    let html = index(State(state.clone()), req).await.ok().expect("index rendered").into_string();
+    assert!(html.contains("foo/bar"));
+
+    let req = Request::builder().uri("/foo/bar").body(axum::body::Body::empty()).unwrap();
+    let path = Path(("foo".to_string(), "bar".to_string()));
+    let res = repo_page(State(state.clone()), path, req).await.ok().expect("repo rendered");
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = Request::builder().uri("/foo/baz").body(axum::body::Body::empty()).unwrap();
+    let path = Path(("foo".to_string(), "baz".to_string()));
+    let res = repo_page(State(state.clone()), path, req).await.ok().expect("not found rendered");
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // anonymized private repo page served by alias only
+    let repo = Repo { id: 2, full_name: "foo/secret".to_string(), private: true, ..repo };
+    db.seed_repo(&repo, &date).await;
+
+    let req = Request::builder().uri("/?anonymize=1").body(axum::body::Body::empty()).unwrap();
+    let html = index(State(state.clone()), req).await.ok().expect("index rendered").into_string();
+    assert!(html.contains("/-/private-repo-1") && !html.contains("foo/secret"));
+
+    let req = Request::builder().uri("/foo/secret?anonymize=1").body(axum::body::Body::empty());
+    let path = Path(("foo".to_string(), "secret".to_string()));
+    let res = repo_page(State(state.clone()), path, req.unwrap()).await.ok().expect("redirect");
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()[header::LOCATION], "/-/private-repo-1");
+
+    let req = Request::builder().uri("/-/private-repo-1").body(axum::body::Body::empty()).unwrap();
+    let path = Path(("-".to_string(), "private-repo-1".to_string()));
+    let res = repo_page(State(state.clone()), path, req).await.ok().expect("repo rendered");
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    assert!(!String::from_utf8_lossy(&body).contains("secret"));
+
+    // alias of hidden repo not resolved, its name not shown on not found page
+    let repo = Repo { id: 3, full_name: "foo/gone".to_string(), ..repo };
+    db.insert_repo(&repo).await.unwrap();
+    db.mark_repo_hidden(&vec![3]).await.unwrap();
+
+    let req = Request::builder().uri("/-/private-repo-2").body(axum::body::Body::empty()).unwrap();
+    let path = Path(("-".to_string(), "private-repo-2".to_string()));
+    let res = repo_page(State(state), path, req).await.ok().expect("not found rendered");
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    assert!(!String::from_utf8_lossy(&body).contains("foo/gone"));
+  }
+}
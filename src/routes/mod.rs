@@ -3,8 +3,10 @@ mod html;
 
 use std::sync::Arc;
 
+use axum::extract::{DefaultBodyLimit, Request};
 use axum::http::StatusCode;
-use axum::{extract::Request, middleware::Next, response::IntoResponse, routing::get, Router};
+use axum::routing::{get, post};
+use axum::{middleware::Next, response::IntoResponse, Router};
 use reqwest::Method;
 use tower_http::cors::{Any, CorsLayer};
 
@@ -29,12 +31,34 @@ pub fn api_routes() -> Router<Arc<AppState>> {
 
   let router = Router::new()
     .route("/repos", get(api::api_get_repos))
+    .route("/repos/:owner/:repo", get(api::api_get_repo))
+    .route("/repos/:owner/:repo/referrers", get(api::api_get_referrers))
+    .route("/repos/:owner/:repo/paths", get(api::api_get_paths))
+    .route("/export", get(api::api_export))
+    .route("/export.jsonl", get(api::api_export_jsonl))
+    .route("/import", post(api::api_import).layer(DefaultBodyLimit::max(512 * 1024 * 1024)))
+    .route("/rate_limit", get(api::api_rate_limit))
+    .route("/status", get(api::api_status))
+    .route("/config", get(api::api_config))
     .layer(axum::middleware::from_fn(check_api_token))
+    .route("/openapi.json", get(api::api_openapi)) // public, added after token check
     .layer(cors);
 
   router
 }
 
+/// Prometheus / OpenMetrics scrape endpoint, protected same as API
+pub fn metrics_routes() -> Router<Arc<AppState>> {
+  Router::new()
+    .route("/metrics", get(api::api_metrics))
+    .layer(axum::middleware::from_fn(check_api_token))
+}
+
 pub fn html_routes() -> Router<Arc<AppState>> {
-  Router::new().route("/", get(html::index)).route("/:owner/:repo", get(html::repo_page))
+  Router::new()
+    .route("/", get(html::index))
+    .route("/compare", get(html::compare_page))
+    .route("/favicon", get(html::favicon))
+    .route("/:owner/:repo", get(html::repo_page))
+    .route("/:owner/:repo/badge/:metric", get(html::repo_badge))
 }
@@ -1,9 +1,16 @@
-use std::sync::Mutex;
+use std::{collections::HashMap, sync::Mutex};
+
+use axum::{
+  extract::Query,
+  http::{StatusCode, Uri},
+};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-  db_client::{DbClient, RepoFilter, RepoTotals},
-  gh_client::GhClient,
-  helpers::GhsFilter,
+  db_client::{DbClient, Direction, RepoFilter, RepoSort, RepoTotals},
+  gh_client::{GhClient, RateLimit, Visibility},
+  helpers::{parse_columns, GhsFilter},
   types::Res,
 };
 
@@ -12,41 +19,289 @@ fn env_bool(key: &str) -> bool {
   return val == "true" || val == "1";
 }
 
+fn env_enum<T: DeserializeOwned + Default>(key: &str) -> Res<T> {
+  match std::env::var(key) {
+    Ok(val) => match serde_json::from_value(serde_json::Value::String(val.clone())) {
+      Ok(val) => Ok(val),
+      Err(_) => anyhow::bail!("invalid {} value: {}", key, val),
+    },
+    Err(_) => Ok(T::default()),
+  }
+}
+
+fn favicon_content_type(path: &str) -> &'static str {
+  let ext = std::path::Path::new(path).extension().and_then(|x| x.to_str()).unwrap_or_default();
+  match ext.to_lowercase().as_str() {
+    "svg" => "image/svg+xml",
+    "png" => "image/png",
+    "jpg" | "jpeg" => "image/jpeg",
+    "gif" => "image/gif",
+    "webp" => "image/webp",
+    _ => "image/x-icon",
+  }
+}
+
 pub struct AppState {
   pub db: DbClient,
   pub gh: GhClient,
   pub filter: GhsFilter,
   pub include_private: bool,
+  pub visibility: Visibility,
+  pub affiliation: String,
+  pub org: Option<String>,
+  pub sync_on_start: bool,
+  pub skip_initial_sync: bool,
+  pub show_deltas: bool,
+  pub hide_inactive: bool,
+  pub skip_fork_traffic: bool,
+  pub track_starred: bool,
+  pub spike_alert_factor: Option<f64>,
+  pub webhook_url: Option<String>,
+  pub concurrency: usize,
+  pub brand_name: String,
+  pub favicon: Option<(String, Vec<u8>)>, // content type, file body
+  pub custom_css: Option<String>,
+  pub pinned: Vec<String>,
+  pub columns: Vec<String>,
+  pub default_sort: RepoSort,
+  pub default_direction: Direction,
+  pub update_check: bool,
   pub last_release: Mutex<String>,
+  pub last_update: Mutex<Option<DateTime<Utc>>>,
+  pub last_run: Mutex<Option<RunStatus>>,
+  pub db_path: String,
+  pub rate_limit: Mutex<Option<RateLimit>>,
+  pub spike_alerts: Mutex<HashMap<String, String>>, // repo name -> last alerted date
+}
+
+/// Outcome of last metrics update (cron or initial sync)
+#[derive(Debug, Clone, Serialize)]
+pub struct RunStatus {
+  pub started_at: DateTime<Utc>,
+  pub finished_at: DateTime<Utc>,
+  pub duration_ms: u64,
+  pub repos: usize,
+  pub errors: usize,  // repos failed to update
+  pub new_stars: i64, // stars gained by synced repos since previous run
+  pub error: Option<String>,
 }
 
 impl AppState {
   pub async fn new() -> Res<Self> {
+    let app_id = std::env::var("GHS_APP_ID").unwrap_or_default();
     let gh_token = std::env::var("GITHUB_TOKEN").unwrap_or_default();
-    if gh_token.is_empty() {
-      tracing::error!("missing GITHUB_TOKEN");
-      std::process::exit(1);
+    if !app_id.is_empty() {
+      tracing::info!("using github app auth, app id: {}", app_id);
+    } else if gh_token.is_empty() {
+      if !env_bool("GHS_ALLOW_ANONYMOUS") {
+        tracing::error!("missing GITHUB_TOKEN");
+        std::process::exit(1);
+      }
+
+      tracing::warn!("no GITHUB_TOKEN, anonymous mode: rate limit 60 req/h, no traffic data");
     }
 
-    let db_path = std::env::var("DB_PATH").unwrap_or("./data/ghstats.db".to_string());
+    let db_path = match std::env::var("DB_PATH") {
+      Ok(path) => path,
+      Err(_) => {
+        let data_dir = std::env::var("GHS_DATA_DIR").unwrap_or("./data".to_string());
+        std::path::Path::new(&data_dir).join("ghstats.db").to_string_lossy().to_string()
+      }
+    };
     tracing::info!("db_path: {}", db_path);
 
     let db = DbClient::new(&db_path).await?;
-    let gh = GhClient::new(gh_token)?;
+    let gh = match app_id.is_empty() {
+      true => GhClient::new(gh_token)?,
+      false => {
+        let installation_id = std::env::var("GHS_INSTALLATION_ID").unwrap_or_default();
+        let private_key = std::env::var("GHS_APP_PRIVATE_KEY").unwrap_or_default();
+        if installation_id.is_empty() || private_key.is_empty() {
+          anyhow::bail!("GHS_APP_ID requires GHS_INSTALLATION_ID and GHS_APP_PRIVATE_KEY");
+        }
+
+        GhClient::new_app(app_id, installation_id, private_key)?
+      }
+    };
+
+    let mut filter = std::env::var("GHS_FILTER").unwrap_or_default();
+    if let Ok(path) = std::env::var("GHS_FILTER_FILE") {
+      match std::fs::read_to_string(&path) {
+        Ok(text) => {
+          let rules =
+            text.lines().map(|x| x.trim()).filter(|x| !x.is_empty() && !x.starts_with('#'));
+          let rules = rules.collect::<Vec<_>>().join(",");
+          filter = format!("{},{}", filter, rules);
+        }
+        Err(e) => tracing::error!("failed to read GHS_FILTER_FILE {}: {:?}", path, e),
+      }
+    }
 
-    let filter = std::env::var("GHS_FILTER").unwrap_or_default();
     let filter = GhsFilter::new(&filter);
     tracing::info!("{:?}", filter);
 
-    let include_private = env_bool("GHS_INCLUDE_PRIVATE");
+    // GHS_INCLUDE_PRIVATE kept as shortcut for GHS_VISIBILITY=all
+    let visibility: Visibility = match std::env::var("GHS_VISIBILITY") {
+      Ok(_) => env_enum("GHS_VISIBILITY")?,
+      Err(_) if env_bool("GHS_INCLUDE_PRIVATE") => Visibility::All,
+      Err(_) => Visibility::Public,
+    };
+    let include_private = visibility != Visibility::Public;
+
+    let affiliation = std::env::var("GHS_AFFILIATION").unwrap_or_default().replace(" ", "");
+    let affiliations = ["owner", "collaborator", "organization_member"];
+    for x in affiliation.split(',').filter(|x| !x.is_empty()) {
+      if !affiliations.contains(&x) {
+        anyhow::bail!("invalid GHS_AFFILIATION value: {}, expected {:?}", x, affiliations);
+      }
+    }
+
+    // single organization mode: repos listed from org instead of user (works without token too)
+    let org = std::env::var("GHS_ORG").unwrap_or_default().trim().to_string();
+    let org = match org.is_empty() {
+      true => None,
+      false if org.contains('/') => anyhow::bail!("invalid GHS_ORG value: {}", org),
+      false => Some(org),
+    };
+    if let Some(org) = &org {
+      tracing::info!("tracking repos of {} organization", org);
+      if !affiliation.is_empty() {
+        tracing::warn!("GHS_AFFILIATION ignored, because GHS_ORG is set");
+      }
+    }
+    let sync_on_start = env_bool("GHS_SYNC_ON_START");
+    let skip_initial_sync = env_bool("GHS_SKIP_INITIAL_SYNC");
+    if sync_on_start && skip_initial_sync {
+      tracing::warn!("GHS_SYNC_ON_START ignored, because GHS_SKIP_INITIAL_SYNC is set");
+    }
+    let show_deltas = env_bool("GHS_SHOW_DELTAS");
+    let hide_inactive = env_bool("GHS_HIDE_INACTIVE");
+    let skip_fork_traffic = env_bool("GHS_SKIP_FORK_TRAFFIC");
+    let track_starred = env_bool("GHS_TRACK_STARRED");
 
+    // alert when day views exceed trailing average by factor, disabled by default
+    let spike_alert_factor = match std::env::var("GHS_SPIKE_ALERT_FACTOR") {
+      Ok(val) => match val.trim().parse::<f64>() {
+        Ok(x) if x > 1.0 => Some(x),
+        _ => anyhow::bail!("invalid GHS_SPIKE_ALERT_FACTOR value: {}, expected number > 1", val),
+      },
+      Err(_) => None,
+    };
+    let webhook_url = std::env::var("GHS_WEBHOOK_URL").ok().filter(|x| !x.trim().is_empty());
+
+    // number of repos updated in parallel, keep small to not hit secondary rate limits
+    let concurrency = std::env::var("GHS_CONCURRENCY").unwrap_or_default();
+    let concurrency = concurrency.parse::<usize>().ok().filter(|x| *x > 0).unwrap_or(4);
+
+    let brand_name = std::env::var("GHS_BRAND_NAME").unwrap_or_default().trim().to_string();
+    let brand_name = match brand_name.is_empty() {
+      true => env!("CARGO_PKG_NAME").to_string(),
+      false => brand_name,
+    };
+
+    let favicon = match std::env::var("GHS_FAVICON_PATH") {
+      Ok(path) => match std::fs::read(&path) {
+        Ok(body) => Some((favicon_content_type(&path).to_string(), body)),
+        Err(e) => {
+          tracing::error!("failed to read GHS_FAVICON_PATH {}: {:?}", path, e);
+          None
+        }
+      },
+      Err(_) => None,
+    };
+    // extra stylesheet added after bundled one, e.g. to recolor charts
+    let custom_css = match std::env::var("GHS_CUSTOM_CSS") {
+      Ok(path) => match std::fs::read_to_string(&path) {
+        Ok(body) => Some(body),
+        Err(e) => {
+          tracing::error!("failed to read GHS_CUSTOM_CSS {}: {:?}", path, e);
+          None
+        }
+      },
+      Err(_) => None,
+    };
+
+    // repos always shown first in list, in given order
+    let pinned = std::env::var("GHS_PINNED").unwrap_or_default();
+    let pinned = pinned.split(',').map(|x| x.trim().to_string()).filter(|x| !x.is_empty());
+    let pinned = pinned.collect::<Vec<_>>();
+
+    // which columns of home table shown and in what order
+    let columns = parse_columns(&std::env::var("GHS_COLUMNS").unwrap_or_default())?;
+
+    let default_sort: RepoSort = env_enum("GHS_DEFAULT_SORT")?;
+    let default_direction: Direction = env_enum("GHS_DEFAULT_DIRECTION")?;
+
+    // hourly check of new ghstats release on GitHub, can be disabled for air-gapped setups
+    let update_check = !env_bool("GHS_DISABLE_UPDATE_CHECK");
     let last_release = Mutex::new(env!("CARGO_PKG_VERSION").to_string());
-    Ok(Self { db, gh, filter, include_private, last_release })
+    let last_update = Mutex::new(None);
+    let last_run = Mutex::new(None);
+    let rate_limit = Mutex::new(None);
+    let spike_alerts = Mutex::new(HashMap::new());
+    Ok(Self {
+      db,
+      gh,
+      filter,
+      include_private,
+      visibility,
+      affiliation,
+      org,
+      sync_on_start,
+      skip_initial_sync,
+      show_deltas,
+      hide_inactive,
+      skip_fork_traffic,
+      track_starred,
+      spike_alert_factor,
+      webhook_url,
+      concurrency,
+      brand_name,
+      favicon,
+      custom_css,
+      pinned,
+      columns,
+      default_sort,
+      default_direction,
+      update_check,
+      last_release,
+      last_update,
+      last_run,
+      db_path,
+      rate_limit,
+      spike_alerts,
+    })
+  }
+
+  /// Parse repos filter from query string, missing sort / direction taken from defaults
+  pub fn get_repo_filter(&self, uri: &Uri) -> Res<RepoFilter> {
+    let Query(mut qs): Query<RepoFilter> = Query::try_from_uri(uri)?;
+    let Query(raw): Query<HashMap<String, String>> = Query::try_from_uri(uri)?;
+
+    if !raw.contains_key("sort") {
+      qs.sort = self.default_sort.clone();
+    }
+
+    if !raw.contains_key("direction") {
+      qs.direction = self.default_direction.clone();
+    }
+
+    if qs.active.is_none() && self.hide_inactive {
+      qs.active = Some(true);
+    }
+
+    if qs.owner.as_deref().is_some_and(|x| x.contains('/')) {
+      return Err(anyhow::anyhow!(StatusCode::BAD_REQUEST).context("owner must not contain '/'"));
+    }
+
+    Ok(qs)
   }
 
   pub async fn get_repos_filtered(&self, qs: &RepoFilter) -> Res<Vec<RepoTotals>> {
     let repos = self.db.get_repos(&qs).await?;
-    let repos = repos.into_iter().filter(|x| self.filter.is_included(&x.name, x.fork, x.archived));
+    let repos = repos
+      .into_iter()
+      .filter(|x| self.filter.is_included(&x.name, x.fork, x.archived, x.stars as u32));
     let repos = repos.collect::<Vec<_>>();
     Ok(repos)
   }
@@ -7,7 +7,7 @@ pub type HtmlRes = Result<maud::Markup, AppError>;
 pub struct AppError(anyhow::Error);
 
 impl AppError {
-  pub fn not_found() -> HtmlRes {
+  pub fn not_found<T>() -> Result<T, Self> {
     Err(Self(anyhow::anyhow!(axum::http::StatusCode::NOT_FOUND)))
   }
 }
@@ -3,18 +3,34 @@ use tracing::{dispatcher, Dispatch, Level};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::{EnvFilter, Registry};
 
+/// Default log level from `GHS_LOG_LEVEL` (`info` if not set or invalid)
+fn default_level(val: &str) -> Level {
+  val.trim().parse::<Level>().unwrap_or(Level::INFO)
+}
+
 pub fn init_logger() {
-  let logfmt = tracing_logfmt::builder()
-    .with_target(false)
-    .with_span_name(false)
-    .with_span_path(false)
-    .with_ansi_color(false);
+  // `RUST_LOG` has priority, `GHS_LOG_LEVEL` is simpler knob for default level
+  let level = default_level(&std::env::var("GHS_LOG_LEVEL").unwrap_or_default());
+  let env_filter = || EnvFilter::builder().with_default_directive(level.into()).from_env_lossy();
 
-  let subscriber = Registry::default()
-    .with(EnvFilter::builder().with_default_directive(Level::INFO.into()).from_env_lossy())
-    .with(logfmt.layer());
+  let format = std::env::var("GHS_LOG_FORMAT").unwrap_or_default().to_lowercase();
+  let dispatch = match format.as_str() {
+    "json" => {
+      let json = tracing_subscriber::fmt::layer().json().with_target(false);
+      Dispatch::new(Registry::default().with(env_filter()).with(json))
+    }
+    _ => {
+      let logfmt = tracing_logfmt::builder()
+        .with_target(false)
+        .with_span_name(false)
+        .with_span_path(false)
+        .with_ansi_color(false);
 
-  dispatcher::set_global_default(Dispatch::new(subscriber)).expect("failed to set global logger");
+      Dispatch::new(Registry::default().with(env_filter()).with(logfmt.layer()))
+    }
+  };
+
+  dispatcher::set_global_default(dispatch).expect("failed to set global logger");
 }
 
 // https://github.com/tokio-rs/axum/discussions/1894
@@ -35,3 +51,27 @@ pub async fn shutdown_signal() {
       _ = terminate => {},
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tracing::level_filters::LevelFilter;
+
+  #[test]
+  fn test_default_level() {
+    assert_eq!(default_level("debug"), Level::DEBUG);
+    assert_eq!(default_level(" WARN "), Level::WARN);
+    assert_eq!(default_level("error"), Level::ERROR);
+    assert_eq!(default_level(""), Level::INFO);
+    assert_eq!(default_level("verbose"), Level::INFO);
+
+    let filter = |level: &str, rust_log: &str| {
+      let builder = EnvFilter::builder().with_default_directive(default_level(level).into());
+      builder.parse_lossy(rust_log).max_level_hint()
+    };
+
+    assert_eq!(filter("debug", ""), Some(LevelFilter::DEBUG));
+    assert_eq!(filter("", ""), Some(LevelFilter::INFO));
+    assert_eq!(filter("debug", "warn"), Some(LevelFilter::WARN)); // RUST_LOG wins
+  }
+}